@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, UdpSocket};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use artnet_protocol::{ArtCommand, ArtTalkToMe, Poll, PollReply};
+use chrono::Local;
+
+use crate::protocols::ArtnetNode;
+
+// Art-Net asks controllers to poll every 2.5 to 3 seconds.
+pub const POLL_INTERVAL: Duration = Duration::from_millis(2500);
+
+// A node that has missed this many polls in a row is considered gone.
+const NODE_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct TrackedNode {
+    node: ArtnetNode,
+    last_seen_at: Instant,
+}
+
+// Nodes keyed by IP and bind index, so every port group of a large node gets its own row.
+pub type NodeTable = Arc<Mutex<HashMap<(Ipv4Addr, u8), TrackedNode>>>;
+
+pub fn send_poll(socket: &UdpSocket, broadcast_ip: &str) {
+    let poll = ArtCommand::Poll(Poll {
+        talk_to_me: ArtTalkToMe::EMIT_CHANGES,
+        ..Poll::default()
+    });
+    match poll.write_to_buffer() {
+        Ok(bytes) => {
+            if let Err(e) = socket.send_to(&bytes, format!("{}:6454", broadcast_ip)) {
+                eprintln!("Failed to send ArtPoll to {}: {}", broadcast_ip, e);
+            }
+        }
+        Err(e) => eprintln!("Failed to encode ArtPoll: {}", e),
+    }
+}
+
+// Returns true when the reply came from a node that was not in the table yet.
+pub fn handle_reply(nodes: &NodeTable, reply: &PollReply) -> bool {
+    let node = to_node(reply);
+    let key = (reply.address, reply.bind_index);
+    nodes.lock().unwrap()
+        .insert(key, TrackedNode { node, last_seen_at: Instant::now() })
+        .is_none()
+}
+
+pub fn expire_nodes(nodes: &NodeTable) {
+    nodes.lock().unwrap().retain(|_, n| n.last_seen_at.elapsed() < NODE_TIMEOUT);
+}
+
+pub fn list_nodes(nodes: &NodeTable) -> Vec<ArtnetNode> {
+    let table = nodes.lock().unwrap();
+    let mut keys: Vec<&(Ipv4Addr, u8)> = table.keys().collect();
+    keys.sort();
+    keys.into_iter().map(|k| table[k].node.clone()).collect()
+}
+
+fn to_node(reply: &PollReply) -> ArtnetNode {
+    let net = (reply.port_address[0] & 0x7F) as u16;
+    let sub_net = (reply.port_address[1] & 0x0F) as u16;
+    let port_count = (reply.num_ports[1] as usize).min(4);
+
+    let mut input_universes = Vec::new();
+    let mut output_universes = Vec::new();
+    for i in 0..port_count {
+        let port_type = reply.port_types[i];
+        if port_type & 0x40 != 0 {
+            input_universes.push((net << 8) | (sub_net << 4) | (reply.swin[i] & 0x0F) as u16);
+        }
+        if port_type & 0x80 != 0 {
+            output_universes.push((net << 8) | (sub_net << 4) | (reply.swout[i] & 0x0F) as u16);
+        }
+    }
+
+    ArtnetNode {
+        ip: reply.address.to_string(),
+        bind_index: reply.bind_index,
+        short_name: fixed_string(&reply.short_name),
+        long_name: fixed_string(&reply.long_name),
+        node_report: fixed_string(&reply.node_report),
+        esta_code: reply.esta_code,
+        oem: u16::from_be_bytes(reply.oem),
+        firmware: u16::from_be_bytes(reply.version),
+        mac: reply.mac.iter()
+            .map(|b| format!("{:02X}", b))
+            .collect::<Vec<_>>()
+            .join(":"),
+        input_universes,
+        output_universes,
+        status_1: reply.status_1,
+        status_2: reply.status_2,
+        last_seen: Local::now().format("%H:%M:%S%.3f").to_string(),
+    }
+}
+
+fn fixed_string(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).trim().to_string()
+}
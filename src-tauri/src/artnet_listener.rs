@@ -1,14 +1,28 @@
 use std::sync::mpsc;
 use std::net::UdpSocket;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::collections::HashSet;
-use artnet_protocol::ArtCommand;
+use artnet_protocol::{ArtCommand, FrameType};
 
+use crate::artnet_discovery::{self, NodeTable};
 use crate::protocols;
+use crate::timecode;
 
-pub fn start(tx: mpsc::Sender<protocols::DmxData>, command_rx: mpsc::Receiver<protocols::ArtnetCommand>) {
+// Older nodes send ArtPollReply packets shorter than the current spec; the
+// missing trailing fields are defined as zero so they are padded before decoding.
+const POLL_REPLY_LENGTH: usize = 239;
+
+pub fn start(
+    tx: mpsc::Sender<protocols::DmxData>,
+    command_rx: mpsc::Receiver<protocols::ArtnetCommand>,
+    nodes: NodeTable,
+    nodes_tx: mpsc::Sender<Vec<protocols::ArtnetNode>>,
+    timecode_tx: mpsc::Sender<protocols::TimecodeData>,
+) {
     let mut socket: Option<UdpSocket> = None;
     let mut subscribed_universes: HashSet<u16> = HashSet::new();
+    let mut discovery_broadcast: Option<String> = None;
+    let mut last_poll: Option<Instant> = None;
     let mut buf = [0u8; 1024];
 
     loop{
@@ -18,6 +32,7 @@ pub fn start(tx: mpsc::Sender<protocols::DmxData>, command_rx: mpsc::Receiver<pr
                 match UdpSocket::bind(addr) {
                     Ok(s) => {
                         s.set_read_timeout(Some(Duration::from_millis(100))).unwrap();
+                        s.set_broadcast(true).ok();
                         socket = Some(s);
                     },
                     Err(e) => {
@@ -29,25 +44,48 @@ pub fn start(tx: mpsc::Sender<protocols::DmxData>, command_rx: mpsc::Receiver<pr
             Ok(protocols::ArtnetCommand::Stop) => {
                 socket = None;
                 subscribed_universes.clear();
-                println!("Art-Net listener stopped.");
+                discovery_broadcast = None;
+                nodes.lock().unwrap().clear();
+                nodes_tx.send(Vec::new()).ok();
+                eprintln!("Art-Net listener stopped.");
             }
             Ok(protocols::ArtnetCommand::SubscribeUniverse(universe)) => {
                 subscribed_universes.insert(universe);
-                println!("Subscribed to Art-Net universe {}", universe);
+                eprintln!("Subscribed to Art-Net universe {}", universe);
             }
             Ok(protocols::ArtnetCommand::UnsubscribeUniverse(universe)) => {
                 subscribed_universes.remove(&universe);
-                println!("Unsubscribed from Art-Net universe {}", universe);
+                eprintln!("Unsubscribed from Art-Net universe {}", universe);
+            }
+            Ok(protocols::ArtnetCommand::StartDiscovery { broadcast_ip }) => {
+                eprintln!("Art-Net discovery started on {}", broadcast_ip);
+                discovery_broadcast = Some(broadcast_ip);
+                last_poll = None;
+            }
+            Ok(protocols::ArtnetCommand::StopDiscovery) => {
+                discovery_broadcast = None;
+                eprintln!("Art-Net discovery stopped.");
             }
+            // No socket and no one left to send Start, so stop.
+            Err(mpsc::TryRecvError::Disconnected) if socket.is_none() => return,
             Err(_) => {}
         }
 
 
         if let Some(ref s) = socket {
+            if let Some(ref broadcast_ip) = discovery_broadcast {
+                if last_poll.is_none_or(|t| t.elapsed() >= artnet_discovery::POLL_INTERVAL) {
+                    artnet_discovery::send_poll(s, broadcast_ip);
+                    artnet_discovery::expire_nodes(&nodes);
+                    nodes_tx.send(artnet_discovery::list_nodes(&nodes)).ok();
+                    last_poll = Some(Instant::now());
+                }
+            }
+
             match s.recv_from(&mut buf) {
-                Ok((length, _src)) => {
-                    if let Ok(command) = ArtCommand::from_buffer(&buf[..length]) {
-                        if let ArtCommand::Output(output) = command {
+                Ok((length, src)) => {
+                    match decode_command(&buf[..length]) {
+                        Some(ArtCommand::Output(output)) => {
                             let universe= output.port_address.into();
 
                             if subscribed_universes.contains(&universe) {
@@ -58,9 +96,42 @@ pub fn start(tx: mpsc::Sender<protocols::DmxData>, command_rx: mpsc::Receiver<pr
                                 tx.send(protocols::DmxData {
                                     universe,
                                     channels,
+                                    source: Some(protocols::DmxSource::Artnet(protocols::ArtnetSourceInfo {
+                                        sender: src.ip().to_string(),
+                                        sequence: output.sequence,
+                                        physical: output.physical,
+                                    })),
+                                    start_code: protocols::START_CODE_LEVELS,
                                 }).ok();
                             }
                         }
+                        Some(ArtCommand::OpTimeCode(tc)) => {
+                            // Stream 0 is the master; other streams are told apart in the source.
+                            let source = match tc.stream_id {
+                                0 => format!("artnet:{}", src.ip()),
+                                id => format!("artnet:{} stream {}", src.ip(), id),
+                            };
+                            let rate = match tc.frame_type {
+                                FrameType::Film => protocols::TimecodeRate::Film,
+                                FrameType::EBU => protocols::TimecodeRate::Ebu,
+                                FrameType::DF => protocols::TimecodeRate::DropFrame,
+                                FrameType::SMPTE => protocols::TimecodeRate::Smpte,
+                            };
+                            timecode_tx.send(timecode::timecode_data(&source, protocols::Timecode {
+                                hours: tc.hours,
+                                minutes: tc.minutes,
+                                seconds: tc.seconds,
+                                frames: tc.frames,
+                                rate,
+                            })).ok();
+                        }
+                        Some(ArtCommand::PollReply(reply)) => {
+                            let is_new = artnet_discovery::handle_reply(&nodes, &reply);
+                            if is_new {
+                                nodes_tx.send(artnet_discovery::list_nodes(&nodes)).ok();
+                            }
+                        }
+                        _ => {}
                     }
                 }
                 Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
@@ -77,4 +148,13 @@ pub fn start(tx: mpsc::Sender<protocols::DmxData>, command_rx: mpsc::Receiver<pr
                 std::thread::sleep(Duration::from_millis(100));
         }
     }
-}
\ No newline at end of file
+}
+
+fn decode_command(packet: &[u8]) -> Option<ArtCommand> {
+    if packet.len() >= 10 && packet.len() < POLL_REPLY_LENGTH && packet[8..10] == [0x00, 0x21] {
+        let mut padded = packet.to_vec();
+        padded.resize(POLL_REPLY_LENGTH, 0);
+        return ArtCommand::from_buffer(&padded).ok();
+    }
+    ArtCommand::from_buffer(packet).ok()
+}
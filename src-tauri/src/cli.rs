@@ -0,0 +1,785 @@
+use std::io::{self, Write};
+use std::sync::{mpsc, Arc};
+use chrono::Local;
+
+use crate::osc_message_data::OscArgData;
+use crate::protocols::{self, DmxData, DmxSource, MidiMessage, OscData, SerialData};
+use crate::recorder::CaptureData;
+use crate::{artnet_discovery, artnet_listener, dmx_bridge, dmx_merge, enttec_listener, ltc_decoder, midi_listener, osc_filter, osc_listener, osc_proxy, sacn_discovery, sacn_listener, serial_framing, serial_listener, timecode};
+
+const USAGE: &str = "\
+Usage: fluxviewer [COMMAND] [OPTIONS]
+
+Starts the monitor window when no command is given.
+
+Commands:
+  osc     [--ip IP] [--port PORT]     Print OSC messages (default 0.0.0.0:8000)
+          [--tcp server|client] [--slip]
+                                      Receive over TCP, length-prefixed (OSC 1.0) or SLIP (OSC 1.1)
+          [--filter PATTERN] [--sender CIDR]
+                                      Only print matching messages, e.g. --filter '/eos/out/*'
+          [--forward HOST:PORT]...    Pass every packet on unchanged while printing it
+  sacn    [--ip IP] --universe LIST   Print sACN universes, e.g. --universe 1-4,7
+          [--merge htp|ltp]           Print each universe merged across its sources instead
+  artnet  [--ip IP] --universe LIST   Print Art-Net universes
+          [--merge htp|ltp]
+  bridge  [--ip IP] --route ROUTE...  Retransmit universes between Art-Net and sACN
+          [--artnet-target IP] [--priority N] [--refresh HZ]
+                                      ROUTE: artnet:0:0:0=sacn:1 or sacn:1=artnet:0:0:0
+  serial  --port NAME [--baud RATE] [--line 8N1] [--flow none|software|hardware]
+          [--frame MODE]              Print serial data (default 115200 8N1)
+                                      MODE: raw, lf, cr, crlf, slip, cobs, idle:MS, fixed:N
+  enttec  --port NAME [--universe N]  Print DMX received by an Enttec DMX USB Pro (default universe 1)
+  midi    [--port NAME]               Print MIDI messages (opens a virtual port without --port)
+  timecode [--ip IP]                  Print Art-Net timecode with drift against the local clock
+  ltc     [--file PATH]               Decode LTC from a WAV file, or WAV on stdin without --file
+  ports                               List available serial ports
+  midi-ports                          List available MIDI input ports
+
+Options:
+  --json      Print one JSON object per line instead of text
+  -h, --help  Show this help
+";
+
+#[derive(Clone, Copy)]
+enum Format {
+    Text,
+    Json,
+}
+
+struct Options {
+    format: Format,
+    ip: String,
+    port: Option<String>,
+    file: Option<String>,
+    osc_tcp: Option<String>,
+    osc_slip: bool,
+    osc_filter: Option<String>,
+    osc_sender: Option<String>,
+    osc_forward: Vec<String>,
+    baud_rate: u32,
+    serial_config: protocols::SerialLineConfig,
+    serial_framing: protocols::SerialFraming,
+    universes: Vec<u16>,
+    merge_mode: Option<protocols::DmxMergeMode>,
+    bridge_routes: Vec<protocols::DmxBridgeRoute>,
+    artnet_target: Option<String>,
+    sacn_priority: u8,
+    refresh_rate: f64,
+}
+
+// Returns None when the arguments don't name a command so the GUI starts as usual.
+pub fn run(args: &[String]) -> Option<i32> {
+    let command = args.first()?.as_str();
+    if command == "-h" || command == "--help" {
+        print!("{}", USAGE);
+        return Some(0);
+    }
+    if !matches!(command, "osc" | "sacn" | "artnet" | "bridge" | "serial" | "enttec" | "midi" | "timecode" | "ltc" | "ports" | "midi-ports") {
+        return None;
+    }
+
+    let options = match parse_options(&args[1..]) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            return Some(2);
+        }
+    };
+
+    let result = match command {
+        "osc" => run_osc(&options),
+        "sacn" => run_sacn(&options),
+        "artnet" => run_artnet(&options),
+        "bridge" => run_bridge(&options),
+        "serial" => run_serial(&options),
+        "enttec" => run_enttec(&options),
+        "midi" => run_midi(&options),
+        "timecode" => run_timecode(&options),
+        "ltc" => run_ltc(&options),
+        "midi-ports" => list_midi_ports(&options),
+        _ => list_ports(&options),
+    };
+
+    match result {
+        Ok(()) => Some(0),
+        // Downstream closed the pipe, e.g. `fluxviewer osc | head`.
+        Err(ref e) if e.kind() == io::ErrorKind::BrokenPipe => Some(0),
+        Err(e) => {
+            eprintln!("{}", e);
+            Some(1)
+        }
+    }
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        format: Format::Text,
+        ip: "0.0.0.0".to_string(),
+        port: None,
+        file: None,
+        osc_tcp: None,
+        osc_slip: false,
+        osc_filter: None,
+        osc_sender: None,
+        osc_forward: Vec::new(),
+        baud_rate: 115200,
+        serial_config: protocols::SerialLineConfig::default(),
+        serial_framing: protocols::SerialFraming::Raw,
+        universes: Vec::new(),
+        merge_mode: None,
+        bridge_routes: Vec::new(),
+        artnet_target: None,
+        sacn_priority: sacn::packet::E131_DEFAULT_PRIORITY,
+        refresh_rate: 1.0,
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("Missing value for {}", arg));
+        match arg.as_str() {
+            "--json" => options.format = Format::Json,
+            "--ip" => options.ip = value()?.clone(),
+            "--port" => options.port = Some(value()?.clone()),
+            "--file" => options.file = Some(value()?.clone()),
+            "--tcp" => options.osc_tcp = Some(value()?.clone()),
+            "--slip" => options.osc_slip = true,
+            "--filter" => options.osc_filter = Some(value()?.clone()),
+            "--sender" => options.osc_sender = Some(value()?.clone()),
+            "--forward" => options.osc_forward.push(value()?.clone()),
+            "--baud" => {
+                let baud = value()?;
+                options.baud_rate = baud.parse().map_err(|_| format!("Invalid baud rate {}", baud))?;
+            }
+            "--line" => parse_line_format(value()?, &mut options.serial_config)?,
+            "--flow" => {
+                options.serial_config.flow_control = match value()?.as_str() {
+                    "none" => protocols::SerialFlowControl::None,
+                    "software" => protocols::SerialFlowControl::Software,
+                    "hardware" => protocols::SerialFlowControl::Hardware,
+                    other => return Err(format!("Invalid flow control {}", other)),
+                };
+            }
+            "--frame" => options.serial_framing = parse_framing(value()?)?,
+            "--universe" => options.universes = parse_universes(value()?)?,
+            "--merge" => {
+                options.merge_mode = Some(match value()?.as_str() {
+                    "htp" => protocols::DmxMergeMode::Htp,
+                    "ltp" => protocols::DmxMergeMode::Ltp,
+                    other => return Err(format!("Invalid merge mode {}", other)),
+                });
+            }
+            "--route" => options.bridge_routes.push(parse_bridge_route(value()?)?),
+            "--artnet-target" => options.artnet_target = Some(value()?.clone()),
+            "--priority" => {
+                let priority = value()?;
+                options.sacn_priority = priority.parse().map_err(|_| format!("Invalid priority {}", priority))?;
+            }
+            "--refresh" => {
+                let rate = value()?;
+                options.refresh_rate = rate.parse().map_err(|_| format!("Invalid refresh rate {}", rate))?;
+            }
+            other => return Err(format!("Unknown option {}", other)),
+        }
+    }
+    Ok(options)
+}
+
+// Reads the usual shorthand such as "8N1" or "7E2".
+fn parse_line_format(spec: &str, config: &mut protocols::SerialLineConfig) -> Result<(), String> {
+    let invalid = || format!("Invalid line format {}", spec);
+    let chars: Vec<char> = spec.to_ascii_uppercase().chars().collect();
+    let [data_bits, parity, stop_bits] = chars[..] else {
+        return Err(invalid());
+    };
+    config.data_bits = data_bits.to_digit(10).ok_or_else(invalid)? as u8;
+    config.parity = match parity {
+        'N' => protocols::SerialParity::None,
+        'O' => protocols::SerialParity::Odd,
+        'E' => protocols::SerialParity::Even,
+        _ => return Err(invalid()),
+    };
+    config.stop_bits = stop_bits.to_digit(10).ok_or_else(invalid)? as u8;
+    Ok(())
+}
+
+fn parse_framing(spec: &str) -> Result<protocols::SerialFraming, String> {
+    let invalid = || format!("Invalid frame mode {}", spec);
+    let delimiter = |d: &[u8]| protocols::SerialFraming::Delimiter { delimiter: d.to_vec(), keep_delimiter: false };
+    let framing = match spec.split_once(':') {
+        None => match spec {
+            "raw" => protocols::SerialFraming::Raw,
+            "lf" => delimiter(b"\n"),
+            "cr" => delimiter(b"\r"),
+            "crlf" => delimiter(b"\r\n"),
+            "slip" => protocols::SerialFraming::Slip,
+            "cobs" => protocols::SerialFraming::Cobs,
+            _ => return Err(invalid()),
+        },
+        Some(("idle", ms)) => protocols::SerialFraming::Idle { timeout_ms: ms.parse().map_err(|_| invalid())? },
+        Some(("fixed", length)) => protocols::SerialFraming::Fixed { length: length.parse().map_err(|_| invalid())? },
+        Some(_) => return Err(invalid()),
+    };
+    serial_framing::validate(&framing)?;
+    Ok(framing)
+}
+
+// Accepts comma separated universes and inclusive ranges, e.g. "1-4,7".
+fn parse_universes(spec: &str) -> Result<Vec<u16>, String> {
+    let invalid = || format!("Invalid universe list {}", spec);
+    let mut universes = Vec::new();
+    for part in spec.split(',').map(str::trim) {
+        match part.split_once('-') {
+            Some((first, last)) => {
+                let first: u16 = first.trim().parse().map_err(|_| invalid())?;
+                let last: u16 = last.trim().parse().map_err(|_| invalid())?;
+                if first > last {
+                    return Err(invalid());
+                }
+                universes.extend(first..=last);
+            }
+            None => universes.push(part.parse().map_err(|_| invalid())?),
+        }
+    }
+    universes.sort_unstable();
+    universes.dedup();
+    Ok(universes)
+}
+
+// Reads "artnet:0:0:0=sacn:1" or "sacn:1=artnet:5".
+fn parse_bridge_route(spec: &str) -> Result<protocols::DmxBridgeRoute, String> {
+    let invalid = || format!("Invalid bridge route {}", spec);
+    let (from, to) = spec.split_once('=').ok_or_else(invalid)?;
+    let route = match (from.split_once(':'), to.split_once(':')) {
+        (Some(("artnet", input)), Some(("sacn", output))) => protocols::DmxBridgeRoute {
+            direction: protocols::DmxBridgeDirection::ArtnetToSacn,
+            input: parse_artnet_universe(input).ok_or_else(invalid)?,
+            output: output.parse().map_err(|_| invalid())?,
+        },
+        (Some(("sacn", input)), Some(("artnet", output))) => protocols::DmxBridgeRoute {
+            direction: protocols::DmxBridgeDirection::SacnToArtnet,
+            input: input.parse().map_err(|_| invalid())?,
+            output: parse_artnet_universe(output).ok_or_else(invalid)?,
+        },
+        _ => return Err(invalid()),
+    };
+    Ok(route)
+}
+
+// Either a 15-bit port address or net:sub-net:universe.
+fn parse_artnet_universe(spec: &str) -> Option<u16> {
+    let parts: Vec<u16> = spec.split(':').map(|p| p.parse().ok()).collect::<Option<_>>()?;
+    match parts[..] {
+        [port_address] => Some(port_address),
+        [net, sub_net, universe] if net < 128 && sub_net < 16 && universe < 16 => Some(net << 8 | sub_net << 4 | universe),
+        _ => None,
+    }
+}
+
+fn run_osc(options: &Options) -> io::Result<()> {
+    let port: u16 = match options.port {
+        Some(ref port) => port.parse().map_err(|_| invalid_input(format!("Invalid OSC port {}", port)))?,
+        None => 8000,
+    };
+
+    let framing = match options.osc_slip {
+        true => protocols::OscTcpFraming::Slip,
+        false => protocols::OscTcpFraming::LengthPrefix,
+    };
+    let transport = match options.osc_tcp.as_deref() {
+        None => protocols::OscTransport::Udp,
+        Some("server") => protocols::OscTransport::TcpServer { framing },
+        Some("client") => protocols::OscTransport::TcpClient { framing },
+        Some(other) => return Err(invalid_input(format!("Invalid TCP mode {}", other))),
+    };
+
+    let filter = osc_filter::OscFilter::default();
+    if options.osc_filter.is_some() || options.osc_sender.is_some() {
+        filter.set_rules(vec![protocols::OscFilterRule {
+            action: protocols::OscFilterAction::Allow,
+            address: options.osc_filter.clone(),
+            sender: options.osc_sender.clone(),
+        }]).map_err(invalid_input)?;
+    }
+
+    let proxy = Arc::new(osc_proxy::OscProxy::new().map_err(invalid_input)?);
+    if !options.osc_forward.is_empty() {
+        proxy.configure(Some(protocols::OscProxyConfig {
+            destinations: options.osc_forward.clone(),
+            rewrites: Vec::new(),
+        })).map_err(invalid_input)?;
+    }
+
+    let (tx, rx) = mpsc::channel::<OscData>();
+    let (cmd_tx, cmd_rx) = mpsc::channel::<protocols::OscCommand>();
+    let (connections_tx, _connections_rx) = mpsc::channel::<Vec<protocols::OscConnection>>();
+    std::thread::spawn(move || {
+        osc_listener::start(tx, cmd_rx, osc_listener::ConnectionTable::default(), connections_tx, proxy);
+    });
+    cmd_tx.send(protocols::OscCommand::Start { ip: options.ip.clone(), port, transport }).ok();
+    drop(cmd_tx);
+
+    let mut out = io::stdout().lock();
+    for osc_data in rx.into_iter().filter(|osc| filter.allows(osc)) {
+        match options.format {
+            Format::Text => {
+                let args: Vec<String> = osc_data.message.args.iter().map(format_arg).collect();
+                let bundle = match (osc_data.bundle_id, osc_data.timetag) {
+                    (Some(id), Some(timetag)) => format!(" [bundle {} at {}]", id, timetag),
+                    (Some(id), None) => format!(" [bundle {}]", id),
+                    _ => String::new(),
+                };
+                writeln!(out, "{} {} {} {}{}", osc_data.timestamp, osc_data.sender, osc_data.message.address, args.join(" "), bundle)?;
+            }
+            Format::Json => write_json(&mut out, &CaptureData::Osc(osc_data))?,
+        }
+    }
+    Err(listener_stopped("OSC"))
+}
+
+fn run_sacn(options: &Options) -> io::Result<()> {
+    if options.universes.is_empty() {
+        return Err(invalid_input("sacn needs --universe".to_string()));
+    }
+
+    let (tx, rx) = mpsc::channel::<DmxData>();
+    let (cmd_tx, cmd_rx) = mpsc::channel::<protocols::SacnCommand>();
+    let (discovery_tx, _discovery_rx) = mpsc::channel::<Vec<protocols::SacnDiscoveredSource>>();
+    let sources = sacn_listener::SourceTable::default();
+    let discovered = sacn_discovery::DiscoveryTable::default();
+    std::thread::spawn(move || {
+        sacn_listener::start(tx, cmd_rx, sources, discovered, discovery_tx);
+    });
+    cmd_tx.send(protocols::SacnCommand::Start { ip: options.ip.clone() }).ok();
+    for &universe in &options.universes {
+        cmd_tx.send(protocols::SacnCommand::SubscribeUniverse(universe)).ok();
+    }
+    drop(cmd_tx);
+
+    write_universes(rx, "sacn", options, CaptureData::Sacn)?;
+    Err(listener_stopped("sACN"))
+}
+
+fn run_artnet(options: &Options) -> io::Result<()> {
+    if options.universes.is_empty() {
+        return Err(invalid_input("artnet needs --universe".to_string()));
+    }
+
+    let (tx, rx) = mpsc::channel::<DmxData>();
+    let (cmd_tx, cmd_rx) = mpsc::channel::<protocols::ArtnetCommand>();
+    let (nodes_tx, _nodes_rx) = mpsc::channel::<Vec<protocols::ArtnetNode>>();
+    let (timecode_tx, _timecode_rx) = mpsc::channel::<protocols::TimecodeData>();
+    let nodes = artnet_discovery::NodeTable::default();
+    std::thread::spawn(move || {
+        artnet_listener::start(tx, cmd_rx, nodes, nodes_tx, timecode_tx);
+    });
+    cmd_tx.send(protocols::ArtnetCommand::Start { ip: options.ip.clone() }).ok();
+    for &universe in &options.universes {
+        cmd_tx.send(protocols::ArtnetCommand::SubscribeUniverse(universe)).ok();
+    }
+    drop(cmd_tx);
+
+    write_universes(rx, "artnet", options, CaptureData::Artnet)?;
+    Err(listener_stopped("Art-Net"))
+}
+
+fn write_universes(rx: mpsc::Receiver<DmxData>, protocol: &'static str, options: &Options, capture: fn(DmxData) -> CaptureData) -> io::Result<()> {
+    let mut out = io::stdout().lock();
+    let Some(mode) = options.merge_mode else {
+        for dmx_data in rx {
+            match options.format {
+                Format::Text => write_dmx(&mut out, protocol, &dmx_data)?,
+                Format::Json => write_json(&mut out, &capture(dmx_data))?,
+            }
+        }
+        return Ok(());
+    };
+
+    let merger = dmx_merge::DmxMerger::default();
+    merger.set_mode(None, mode);
+    loop {
+        let mut merged = Vec::new();
+        match rx.recv_timeout(dmx_merge::EXPIRE_INTERVAL) {
+            Ok(dmx_data) => merged.extend(merger.update(protocol, &dmx_data)),
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+        // Sources that stop sending are only dropped here, so this runs when nothing arrives too.
+        merged.extend(merger.expire());
+        for merged in merged {
+            match options.format {
+                Format::Text => write_merge(&mut out, &merged)?,
+                Format::Json => {
+                    serde_json::to_writer(&mut out, &merged)?;
+                    writeln!(out)?;
+                }
+            }
+        }
+    }
+}
+
+// Prints nothing on stdout; routes coming and going are logged to stderr.
+fn run_bridge(options: &Options) -> io::Result<()> {
+    if options.bridge_routes.is_empty() {
+        return Err(invalid_input("bridge needs --route".to_string()));
+    }
+    let config = protocols::DmxBridgeConfig {
+        routes: options.bridge_routes.clone(),
+        sacn_source_name: "FluxViewer Bridge".to_string(),
+        sacn_cid: None,
+        sacn_priority: options.sacn_priority,
+        sacn_unicast: None,
+        artnet_target: options.artnet_target.clone(),
+        refresh_rate: options.refresh_rate,
+    };
+    dmx_bridge::validate(&config).map_err(invalid_input)?;
+
+    let (tx, rx) = mpsc::channel::<DmxData>();
+
+    let (sacn_cmd_tx, sacn_cmd_rx) = mpsc::channel::<protocols::SacnCommand>();
+    let (discovery_tx, _discovery_rx) = mpsc::channel::<Vec<protocols::SacnDiscoveredSource>>();
+    let sacn_tx = tx.clone();
+    std::thread::spawn(move || {
+        sacn_listener::start(sacn_tx, sacn_cmd_rx, sacn_listener::SourceTable::default(), sacn_discovery::DiscoveryTable::default(), discovery_tx);
+    });
+    sacn_cmd_tx.send(protocols::SacnCommand::Start { ip: options.ip.clone() }).ok();
+    for universe in dmx_bridge::input_universes(&config, protocols::DmxBridgeDirection::SacnToArtnet) {
+        sacn_cmd_tx.send(protocols::SacnCommand::SubscribeUniverse(universe)).ok();
+    }
+    drop(sacn_cmd_tx);
+
+    let (artnet_cmd_tx, artnet_cmd_rx) = mpsc::channel::<protocols::ArtnetCommand>();
+    let (nodes_tx, _nodes_rx) = mpsc::channel::<Vec<protocols::ArtnetNode>>();
+    let (timecode_tx, _timecode_rx) = mpsc::channel::<protocols::TimecodeData>();
+    std::thread::spawn(move || {
+        artnet_listener::start(tx, artnet_cmd_rx, artnet_discovery::NodeTable::default(), nodes_tx, timecode_tx);
+    });
+    artnet_cmd_tx.send(protocols::ArtnetCommand::Start { ip: options.ip.clone() }).ok();
+    for universe in dmx_bridge::input_universes(&config, protocols::DmxBridgeDirection::ArtnetToSacn) {
+        artnet_cmd_tx.send(protocols::ArtnetCommand::SubscribeUniverse(universe)).ok();
+    }
+    drop(artnet_cmd_tx);
+
+    let (bridge_tx, bridge_rx) = mpsc::channel::<protocols::DmxBridgeCommand>();
+    std::thread::spawn(move || {
+        dmx_bridge::start(bridge_rx, dmx_bridge::RouteTable::default());
+    });
+    bridge_tx.send(protocols::DmxBridgeCommand::Configure(config)).ok();
+
+    // The bridge sends on the merge of each universe, like the app does.
+    let merger = dmx_merge::DmxMerger::default();
+    loop {
+        let mut merged = Vec::new();
+        match rx.recv_timeout(dmx_merge::EXPIRE_INTERVAL) {
+            Ok(dmx_data) => {
+                let protocol = match dmx_data.source {
+                    Some(protocols::DmxSource::Sacn(_)) => "sacn",
+                    Some(protocols::DmxSource::Artnet(_)) => "artnet",
+                    _ => continue,
+                };
+                merged.extend(merger.update(protocol, &dmx_data));
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+        merged.extend(merger.expire());
+        for m in merged {
+            if bridge_tx.send(protocols::DmxBridgeCommand::Input(m)).is_err() {
+                return Err(listener_stopped("DMX bridge"));
+            }
+        }
+    }
+    Err(listener_stopped("DMX bridge"))
+}
+
+fn run_serial(options: &Options) -> io::Result<()> {
+    let Some(ref port) = options.port else {
+        return Err(invalid_input("serial needs --port".to_string()));
+    };
+
+    let (tx, rx) = mpsc::channel::<SerialData>();
+    let (cmd_tx, cmd_rx) = mpsc::channel::<protocols::SerialCommand>();
+    let (modem_tx, _modem_rx) = mpsc::channel::<protocols::SerialModemLines>();
+    let (midi_tx, _midi_rx) = mpsc::channel::<protocols::MidiData>();
+    std::thread::spawn(move || {
+        serial_listener::start(tx, cmd_rx, serial_listener::OpenPort::default(), modem_tx, midi_tx);
+    });
+    cmd_tx.send(protocols::SerialCommand::SetFraming(options.serial_framing.clone())).ok();
+    cmd_tx.send(protocols::SerialCommand::Start {
+        port: port.clone(),
+        baud_rate: options.baud_rate,
+        config: options.serial_config.clone(),
+    }).ok();
+    drop(cmd_tx);
+
+    let mut out = io::stdout().lock();
+    for serial_data in rx {
+        match options.format {
+            Format::Text => writeln!(out, "{} {}  |{}|", serial_data.timestamp, serial_data.hex, serial_data.ascii)?,
+            Format::Json => write_json(&mut out, &CaptureData::Serial(serial_data))?,
+        }
+    }
+    Err(listener_stopped("Serial"))
+}
+
+fn run_enttec(options: &Options) -> io::Result<()> {
+    let Some(ref port) = options.port else {
+        return Err(invalid_input("enttec needs --port".to_string()));
+    };
+    let universe = options.universes.first().copied().unwrap_or(1);
+
+    let (tx, rx) = mpsc::channel::<DmxData>();
+    let (cmd_tx, cmd_rx) = mpsc::channel::<protocols::EnttecCommand>();
+    let (widget_tx, _widget_rx) = mpsc::channel::<protocols::EnttecWidgetInfo>();
+    std::thread::spawn(move || {
+        enttec_listener::start(tx, cmd_rx, widget_tx);
+    });
+    cmd_tx.send(protocols::EnttecCommand::Start { port: port.clone(), universe, on_change: false }).ok();
+    drop(cmd_tx);
+
+    let mut out = io::stdout().lock();
+    for dmx_data in rx {
+        match options.format {
+            Format::Text => write_dmx(&mut out, "enttec", &dmx_data)?,
+            Format::Json => write_json(&mut out, &CaptureData::Enttec(dmx_data))?,
+        }
+    }
+    Err(listener_stopped("Enttec"))
+}
+
+fn run_midi(options: &Options) -> io::Result<()> {
+    let (tx, rx) = mpsc::channel::<protocols::MidiData>();
+    // midir delivers messages on its own thread while the connection is held.
+    let _connection = match options.port {
+        Some(ref port) => midi_listener::connect(port, tx)
+            .map_err(|e| io::Error::other(format!("Failed to open MIDI port {}: {}", port, e)))?,
+        None => midi_listener::create_virtual("fluxviewer", tx)
+            .map_err(|e| io::Error::other(format!("Failed to create virtual MIDI port fluxviewer: {}", e)))?,
+    };
+
+    let mut out = io::stdout().lock();
+    for midi_data in rx {
+        match options.format {
+            Format::Text => {
+                let hex: Vec<String> = midi_data.bytes.iter().map(|b| format!("{:02X}", b)).collect();
+                writeln!(out, "{} {} {}  {}", midi_data.timestamp, midi_data.source, format_midi(&midi_data.message), hex.join(" "))?;
+            }
+            Format::Json => {
+                serde_json::to_writer(&mut out, &midi_data)?;
+                writeln!(out)?;
+            }
+        }
+    }
+    Err(listener_stopped("MIDI"))
+}
+
+fn run_timecode(options: &Options) -> io::Result<()> {
+    let (tx, _rx) = mpsc::channel::<DmxData>();
+    let (cmd_tx, cmd_rx) = mpsc::channel::<protocols::ArtnetCommand>();
+    let (nodes_tx, _nodes_rx) = mpsc::channel::<Vec<protocols::ArtnetNode>>();
+    let (timecode_tx, timecode_rx) = mpsc::channel::<protocols::TimecodeData>();
+    let nodes = artnet_discovery::NodeTable::default();
+    std::thread::spawn(move || {
+        artnet_listener::start(tx, cmd_rx, nodes, nodes_tx, timecode_tx);
+    });
+    cmd_tx.send(protocols::ArtnetCommand::Start { ip: options.ip.clone() }).ok();
+    drop(cmd_tx);
+
+    write_timecode(timecode_rx, options.format)?;
+    Err(listener_stopped("Art-Net"))
+}
+
+fn run_ltc(options: &Options) -> io::Result<()> {
+    let (timecode_tx, timecode_rx) = mpsc::channel::<protocols::TimecodeData>();
+    let (cmd_tx, cmd_rx) = mpsc::channel::<protocols::LtcCommand>();
+    cmd_tx.send(protocols::LtcCommand::Start { path: options.file.clone() }).ok();
+    // Dropping the command sender lets the decoder thread, and with it the
+    // timecode channel, finish once the input ends.
+    drop(cmd_tx);
+    std::thread::spawn(move || {
+        ltc_decoder::start(timecode_tx, cmd_rx);
+    });
+    write_timecode(timecode_rx, options.format)
+}
+
+fn write_timecode(rx: mpsc::Receiver<protocols::TimecodeData>, format: Format) -> io::Result<()> {
+    let tracker = timecode::TimecodeTracker::default();
+    let mut out = io::stdout().lock();
+    loop {
+        let mut frames = Vec::new();
+        match rx.recv_timeout(timecode::EXPIRE_INTERVAL) {
+            Ok(mut timecode_data) => {
+                if tracker.update(&mut timecode_data) {
+                    frames.push(timecode_data);
+                }
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+        frames.extend(tracker.expire());
+        for timecode_data in frames {
+            write_timecode_data(&mut out, &timecode_data, format)?;
+        }
+    }
+}
+
+fn write_timecode_data(out: &mut impl Write, timecode_data: &protocols::TimecodeData, format: Format) -> io::Result<()> {
+    match format {
+        Format::Text => writeln!(
+            out,
+            "{} {} {} {:?} drift {:+.1} ms dropouts {} skipped {} jumps {}",
+            timecode_data.timestamp,
+            timecode_data.source,
+            timecode::format(&timecode_data.timecode),
+            timecode_data.timecode.rate,
+            timecode_data.drift_ms,
+            timecode_data.dropouts,
+            timecode_data.skipped_frames,
+            timecode_data.jumps
+        ),
+        Format::Json => {
+            serde_json::to_writer(&mut *out, timecode_data)?;
+            writeln!(out)
+        }
+    }
+}
+
+fn list_midi_ports(options: &Options) -> io::Result<()> {
+    let mut out = io::stdout().lock();
+    for port in midi_listener::list_ports() {
+        match options.format {
+            Format::Text => writeln!(out, "{}", port.name)?,
+            Format::Json => {
+                serde_json::to_writer(&mut out, &port)?;
+                writeln!(out)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn list_ports(options: &Options) -> io::Result<()> {
+    let mut out = io::stdout().lock();
+    for port in serial_listener::list_ports() {
+        match options.format {
+            Format::Text => writeln!(out, "{}\t{}", port.name, port.description)?,
+            Format::Json => {
+                serde_json::to_writer(&mut out, &port)?;
+                writeln!(out)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Trailing zero channels are left out so a mostly dark universe stays on one line.
+fn write_dmx(out: &mut impl Write, protocol: &str, dmx: &DmxData) -> io::Result<()> {
+    let source = match dmx.source {
+        Some(DmxSource::Sacn(ref info)) => format!("{} ({}) prio {}", info.name, info.cid, info.priority),
+        Some(DmxSource::Artnet(ref info)) => info.sender.clone(),
+        Some(DmxSource::Enttec(ref info)) => format!("{} ({})", info.port, info.serial_number),
+        None => String::new(),
+    };
+    let start_code = match dmx.start_code {
+        protocols::START_CODE_LEVELS => String::new(),
+        protocols::START_CODE_ADDRESS_PRIORITY => " priority".to_string(),
+        other => format!(" start code 0x{:02X}", other),
+    };
+    let used = dmx.channels.iter().rposition(|&v| v != 0).map_or(0, |i| i + 1);
+    let values: Vec<String> = dmx.channels[..used].iter().map(|v| v.to_string()).collect();
+    writeln!(
+        out,
+        "{} {} {} {}{}: {}",
+        Local::now().format("%H:%M:%S%.3f"),
+        protocol,
+        dmx.universe,
+        source,
+        start_code,
+        values.join(" ")
+    )
+}
+
+fn write_merge(out: &mut impl Write, merged: &protocols::DmxMergeData) -> io::Result<()> {
+    let mode = match merged.mode {
+        protocols::DmxMergeMode::Htp => "htp",
+        protocols::DmxMergeMode::Ltp => "ltp",
+    };
+    let sources: Vec<String> = merged.sources.iter()
+        .map(|s| format!("{} prio {} won {}", s.name, s.priority, s.channels_won))
+        .collect();
+    let used = merged.levels.iter().rposition(|&v| v != 0).map_or(0, |i| i + 1);
+    let values: Vec<String> = merged.levels[..used].iter().map(|v| v.to_string()).collect();
+    writeln!(
+        out,
+        "{} {} {} merged {} [{}]: {}",
+        Local::now().format("%H:%M:%S%.3f"),
+        merged.protocol,
+        merged.universe,
+        mode,
+        sources.join(", "),
+        values.join(" ")
+    )
+}
+
+// Uses the capture file's record layout so each line says which protocol it came from.
+fn write_json(out: &mut impl Write, data: &CaptureData) -> io::Result<()> {
+    serde_json::to_writer(&mut *out, data)?;
+    writeln!(out)
+}
+
+fn format_arg(arg: &OscArgData) -> String {
+    match arg {
+        OscArgData::Int(v) => v.to_string(),
+        OscArgData::Float(v) => v.to_string(),
+        OscArgData::String(v) => format!("{:?}", v),
+        OscArgData::Blob(v) => format!("<blob {} bytes>", v.len()),
+        OscArgData::Bool(v) => v.to_string(),
+        OscArgData::Nil => "nil".to_string(),
+        OscArgData::Inf => "inf".to_string(),
+        OscArgData::Long(v) => format!("{}L", v),
+        OscArgData::Double(v) => format!("{}d", v),
+        OscArgData::Char(v) => format!("{:?}", v),
+        OscArgData::Color { red, green, blue, alpha } => format!("#{:02x}{:02x}{:02x}{:02x}", red, green, blue, alpha),
+        OscArgData::Midi { port, status, data1, data2 } => format!("<midi {} {:02X} {:02X} {:02X}>", port, status, data1, data2),
+        OscArgData::TimeTag { seconds, fractional } => format!("<time {}.{:08x}>", seconds, fractional),
+        OscArgData::Array(items) => format!("[{}]", items.iter().map(format_arg).collect::<Vec<_>>().join(" ")),
+    }
+}
+
+fn format_midi(message: &MidiMessage) -> String {
+    match message {
+        MidiMessage::NoteOff { channel, note, velocity } => format!("ch{} note off {} vel {}", channel, note, velocity),
+        MidiMessage::NoteOn { channel, note, velocity } => format!("ch{} note on {} vel {}", channel, note, velocity),
+        MidiMessage::PolyPressure { channel, note, pressure } => format!("ch{} poly pressure {} {}", channel, note, pressure),
+        MidiMessage::ControlChange { channel, controller, value } => format!("ch{} cc {} = {}", channel, controller, value),
+        MidiMessage::ProgramChange { channel, program } => format!("ch{} program {}", channel, program),
+        MidiMessage::ChannelPressure { channel, pressure } => format!("ch{} pressure {}", channel, pressure),
+        MidiMessage::PitchBend { channel, value } => format!("ch{} pitch bend {}", channel, value),
+        MidiMessage::SysEx { data } => format!("sysex {} bytes", data.len()),
+        MidiMessage::System { status, .. } => format!("system {:02X}", status),
+        MidiMessage::ShowControl { device_id, command_format, command, cue, list, path } => {
+            let mut text = format!("msc dev {} {:?} {:?}", device_id, command_format, command);
+            for (label, value) in [("cue", cue), ("list", list), ("path", path)] {
+                if let Some(value) = value {
+                    text.push_str(&format!(" {} {}", label, value));
+                }
+            }
+            text
+        }
+        MidiMessage::TimecodeQuarterFrame { piece, timecode, .. } => match timecode {
+            Some(tc) => format!("mtc {}", timecode::format(tc)),
+            None => format!("mtc quarter frame {}", piece),
+        },
+        MidiMessage::TimecodeFullFrame { timecode: tc, .. } => format!("mtc full frame {}", timecode::format(tc)),
+    }
+}
+
+fn invalid_input(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+// Listeners only hang up once they have nothing open and no commands left to
+// come, i.e. they failed to start or lost their input.
+fn listener_stopped(name: &str) -> io::Error {
+    io::Error::other(format!("{} listener stopped", name))
+}
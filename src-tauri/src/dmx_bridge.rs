@@ -0,0 +1,278 @@
+use std::collections::HashSet;
+use std::net::Ipv4Addr;
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+use chrono::Local;
+use sacn::packet::E131_MAX_PRIORITY;
+use uuid::Uuid;
+
+use crate::dmx_output;
+use crate::dmx_sender::{ArtnetSender, SacnSender};
+use crate::protocols::{DmxBridgeCommand, DmxBridgeConfig, DmxBridgeDirection, DmxBridgeRoute, DmxBridgeRouteStatus, DmxMergeData};
+
+const DEFAULT_REFRESH: Duration = Duration::from_secs(1);
+// How often due refreshes are checked when no data is arriving.
+const TICK: Duration = Duration::from_millis(100);
+
+pub struct ActiveRoute {
+    route: DmxBridgeRoute,
+    levels: Vec<u8>,
+    last_input: Option<String>,
+    last_input_at: Option<Instant>,
+    last_sent_at: Instant,
+}
+
+// Configured routes in the order they were given.
+pub type RouteTable = Arc<Mutex<Vec<ActiveRoute>>>;
+
+struct Outputs {
+    sacn: Option<SacnSender>,
+    sacn_priority: u8,
+    sacn_unicast: Option<Ipv4Addr>,
+    artnet: Option<(ArtnetSender, Ipv4Addr)>,
+    refresh: Duration,
+}
+
+impl Outputs {
+    fn open(config: &DmxBridgeConfig) -> Outputs {
+        let needs = |direction| config.routes.iter().any(|r| r.direction == direction);
+
+        let sacn = if needs(DmxBridgeDirection::ArtnetToSacn) {
+            let cid = config.sacn_cid.as_deref().and_then(|cid| Uuid::parse_str(cid).ok());
+            match SacnSender::new(&config.sacn_source_name, cid) {
+                Ok(s) => Some(s),
+                Err(e) => {
+                    eprintln!("Failed to open sACN bridge output: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        let artnet = match config.artnet_target.as_deref().and_then(|ip| ip.parse().ok()) {
+            Some(target) if needs(DmxBridgeDirection::SacnToArtnet) => match ArtnetSender::new() {
+                Ok(s) => Some((s, target)),
+                Err(e) => {
+                    eprintln!("Failed to open Art-Net bridge output: {}", e);
+                    None
+                }
+            },
+            _ => None,
+        };
+
+        Outputs {
+            sacn,
+            sacn_priority: config.sacn_priority,
+            sacn_unicast: config.sacn_unicast.as_deref().and_then(|ip| ip.parse().ok()),
+            artnet,
+            refresh: dmx_output::refresh_interval(config.refresh_rate).unwrap_or(DEFAULT_REFRESH),
+        }
+    }
+
+    fn send(&mut self, route: &DmxBridgeRoute, levels: &[u8]) {
+        match route.direction {
+            DmxBridgeDirection::ArtnetToSacn => {
+                if let Some(ref mut sender) = self.sacn {
+                    if let Err(e) = sender.send(route.output, levels, Some(self.sacn_priority), self.sacn_unicast) {
+                        eprintln!("Failed to bridge to sACN universe {}: {}", route.output, e);
+                    }
+                }
+            }
+            DmxBridgeDirection::SacnToArtnet => {
+                if let Some((ref mut sender, target)) = self.artnet {
+                    if let Err(e) = sender.send(route.output, levels, target) {
+                        eprintln!("Failed to bridge to Art-Net universe {}: {}", route.output, e);
+                    }
+                }
+            }
+        }
+    }
+
+    // Art-Net has no way to say a stream has ended, receivers just time out.
+    fn release(&mut self, route: &DmxBridgeRoute) {
+        if route.direction == DmxBridgeDirection::ArtnetToSacn {
+            if let Some(ref mut sender) = self.sacn {
+                if let Err(e) = sender.terminate(route.output) {
+                    eprintln!("Failed to terminate sACN universe {}: {}", route.output, e);
+                }
+            }
+        }
+    }
+}
+
+pub fn start(command_rx: mpsc::Receiver<DmxBridgeCommand>, routes: RouteTable) {
+    let mut outputs: Option<Outputs> = None;
+
+    loop {
+        let timeout = outputs.as_ref().map_or(TICK, |o| o.refresh.min(TICK));
+        match command_rx.recv_timeout(timeout) {
+            Ok(DmxBridgeCommand::Configure(config)) => {
+                let mut table = routes.lock().unwrap();
+                if let Some(ref mut o) = outputs {
+                    release_all(&mut table, o);
+                }
+                let now = Instant::now();
+                *table = config.routes.iter()
+                    .map(|&route| ActiveRoute {
+                        route,
+                        levels: Vec::new(),
+                        last_input: None,
+                        last_input_at: None,
+                        last_sent_at: now,
+                    })
+                    .collect();
+                outputs = Some(Outputs::open(&config));
+                eprintln!("DMX bridge started with {} routes", config.routes.len());
+            }
+            Ok(DmxBridgeCommand::Input(merged)) => {
+                if let Some(ref mut o) = outputs {
+                    input(&mut routes.lock().unwrap(), o, &merged);
+                }
+            }
+            Ok(DmxBridgeCommand::Stop) => {
+                let mut table = routes.lock().unwrap();
+                if let Some(ref mut o) = outputs {
+                    release_all(&mut table, o);
+                }
+                table.clear();
+                outputs = None;
+                eprintln!("DMX bridge stopped");
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => return,
+        }
+
+        if let Some(ref mut o) = outputs {
+            refresh(&mut routes.lock().unwrap(), o);
+        }
+    }
+}
+
+// Takes the merge of every source on a universe, so a main and backup console
+// don't fight over the output, and a route only goes once its last source has
+// terminated or timed out. Per-address priority and preview data are already
+// accounted for by the merge.
+fn input(routes: &mut [ActiveRoute], outputs: &mut Outputs, merged: &DmxMergeData) {
+    let direction = match merged.protocol.as_str() {
+        "artnet" => DmxBridgeDirection::ArtnetToSacn,
+        "sacn" => DmxBridgeDirection::SacnToArtnet,
+        _ => return,
+    };
+
+    let now = Instant::now();
+    for r in routes.iter_mut().filter(|r| r.route.direction == direction && r.route.input == merged.universe) {
+        if merged.sources.is_empty() {
+            lose(r, outputs);
+            continue;
+        }
+        if r.last_input_at.is_none() {
+            eprintln!("DMX bridge receiving {}", describe(&r.route));
+        }
+        r.levels.clone_from(&merged.levels);
+        r.last_input = Some(Local::now().format("%H:%M:%S%.3f").to_string());
+        r.last_input_at = Some(now);
+        r.last_sent_at = now;
+        outputs.send(&r.route, &r.levels);
+    }
+}
+
+// Repeats the last levels of live routes at the refresh rate.
+fn refresh(routes: &mut [ActiveRoute], outputs: &mut Outputs) {
+    let now = Instant::now();
+    for r in routes.iter_mut().filter(|r| r.last_input_at.is_some()) {
+        if now.duration_since(r.last_sent_at) >= outputs.refresh {
+            r.last_sent_at = now;
+            outputs.send(&r.route, &r.levels);
+        }
+    }
+}
+
+fn lose(route: &mut ActiveRoute, outputs: &mut Outputs) {
+    if route.last_input_at.take().is_some() {
+        eprintln!("DMX bridge lost {}", describe(&route.route));
+        outputs.release(&route.route);
+    }
+}
+
+fn release_all(routes: &mut [ActiveRoute], outputs: &mut Outputs) {
+    for r in routes.iter_mut() {
+        if r.last_input_at.take().is_some() {
+            outputs.release(&r.route);
+        }
+    }
+}
+
+fn describe(route: &DmxBridgeRoute) -> String {
+    match route.direction {
+        DmxBridgeDirection::ArtnetToSacn => format!("Art-Net universe {} -> {}", route.input, output_name(route)),
+        DmxBridgeDirection::SacnToArtnet => format!("sACN universe {} -> {}", route.input, output_name(route)),
+    }
+}
+
+fn output_name(route: &DmxBridgeRoute) -> String {
+    match route.direction {
+        DmxBridgeDirection::ArtnetToSacn => format!("sACN universe {}", route.output),
+        DmxBridgeDirection::SacnToArtnet => format!("Art-Net universe {}", route.output),
+    }
+}
+
+pub fn validate(config: &DmxBridgeConfig) -> Result<(), String> {
+    dmx_output::check_refresh_rate(config.refresh_rate)?;
+    if config.sacn_priority > E131_MAX_PRIORITY {
+        return Err(format!("sACN priority must be 0-{}", E131_MAX_PRIORITY));
+    }
+    if let Some(ref cid) = config.sacn_cid {
+        Uuid::parse_str(cid).map_err(|e| format!("Invalid sACN CID {}: {}", cid, e))?;
+    }
+    if let Some(ref ip) = config.sacn_unicast {
+        ip.parse::<Ipv4Addr>().map_err(|e| format!("Invalid sACN unicast address {}: {}", ip, e))?;
+    }
+    if let Some(ref ip) = config.artnet_target {
+        ip.parse::<Ipv4Addr>().map_err(|e| format!("Invalid Art-Net target {}: {}", ip, e))?;
+    }
+
+    let mut outputs = HashSet::new();
+    for route in &config.routes {
+        let (sacn, artnet) = match route.direction {
+            DmxBridgeDirection::ArtnetToSacn => (route.output, route.input),
+            DmxBridgeDirection::SacnToArtnet => (route.input, route.output),
+        };
+        dmx_output::check_sacn_universe(sacn)?;
+        dmx_output::check_artnet_universe(artnet)?;
+        if route.direction == DmxBridgeDirection::SacnToArtnet && config.artnet_target.is_none() {
+            return Err("sACN to Art-Net routes need an Art-Net target".to_string());
+        }
+        if !outputs.insert((route.direction, route.output)) {
+            return Err(format!("More than one route sends to {}", output_name(route)));
+        }
+        // A universe bridged one way and back again would be picked up by the
+        // listeners and sent round in a loop.
+        if config.routes.iter().any(|other| other.direction != route.direction && other.output == route.input) {
+            return Err(format!("{} would feed back into itself", describe(route)));
+        }
+    }
+    Ok(())
+}
+
+pub fn input_universes(config: &DmxBridgeConfig, direction: DmxBridgeDirection) -> Vec<u16> {
+    let mut universes: Vec<u16> = config.routes.iter()
+        .filter(|r| r.direction == direction)
+        .map(|r| r.input)
+        .collect();
+    universes.sort_unstable();
+    universes.dedup();
+    universes
+}
+
+pub fn list_routes(routes: &RouteTable) -> Vec<DmxBridgeRouteStatus> {
+    routes.lock().unwrap().iter()
+        .map(|r| DmxBridgeRouteStatus {
+            direction: r.route.direction,
+            input: r.route.input,
+            output: r.route.output,
+            active: r.last_input_at.is_some(),
+            last_input: r.last_input.clone(),
+        })
+        .collect()
+}
@@ -0,0 +1,253 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use chrono::Local;
+use sacn::packet::{E131_DEFAULT_PRIORITY, E131_NETWORK_DATA_LOSS_TIMEOUT};
+
+use crate::dmx_output::UNIVERSE_SIZE;
+use crate::protocols::{DmxData, DmxMergeData, DmxMergeMode, DmxMergeSource, DmxSource, START_CODE_ADDRESS_PRIORITY, START_CODE_LEVELS};
+
+// Art-Net cancels a merge once a source has been silent for 10 s.
+const ARTNET_MERGE_TIMEOUT: Duration = Duration::from_secs(10);
+// How often to call `DmxMerger::expire`.
+pub const EXPIRE_INTERVAL: Duration = Duration::from_millis(250);
+
+type UniverseKey = (&'static str, u16);
+
+struct Source {
+    name: String,
+    priority: u8,
+    levels: Vec<u8>,
+    // Merge counter value at which each channel last changed, for LTP.
+    changed: Vec<u64>,
+    address_priorities: Option<Vec<u8>>,
+    address_priorities_at: Option<Instant>,
+    last_seen: String,
+    last_seen_at: Instant,
+    timeout: Duration,
+}
+
+impl Source {
+    fn new(timeout: Duration) -> Source {
+        Source {
+            name: String::new(),
+            priority: E131_DEFAULT_PRIORITY,
+            levels: Vec::new(),
+            changed: vec![0; UNIVERSE_SIZE],
+            address_priorities: None,
+            address_priorities_at: None,
+            last_seen: String::new(),
+            last_seen_at: Instant::now(),
+            timeout,
+        }
+    }
+
+    fn set_levels(&mut self, channels: &[u8], counter: u64) {
+        let channels = &channels[..channels.len().min(UNIVERSE_SIZE)];
+        for (i, &level) in channels.iter().enumerate() {
+            if self.levels.get(i) != Some(&level) {
+                self.changed[i] = counter;
+            }
+        }
+        self.levels = channels.to_vec();
+    }
+
+    // None when the source doesn't drive the channel: it sent fewer slots, or
+    // gave the address a per-address priority of 0.
+    fn priority_at(&self, channel: usize) -> Option<u8> {
+        if channel >= self.levels.len() {
+            return None;
+        }
+        match self.address_priorities {
+            Some(ref priorities) => priorities.get(channel).copied().filter(|&p| p > 0),
+            None => Some(self.priority),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Universe {
+    // Ordered by key so ties always go the same way.
+    sources: BTreeMap<String, Source>,
+    counter: u64,
+}
+
+#[derive(Default)]
+struct Modes {
+    default: DmxMergeMode,
+    universes: HashMap<UniverseKey, DmxMergeMode>,
+}
+
+impl Modes {
+    fn get(&self, key: &UniverseKey) -> DmxMergeMode {
+        self.universes.get(key).copied().unwrap_or(self.default)
+    }
+}
+
+// Combines every source sending to a universe the way an E1.31 receiver would:
+// the highest priority wins each address, and sources tied on priority are
+// merged HTP or LTP. Art-Net sources all share the default sACN priority.
+#[derive(Default)]
+pub struct DmxMerger {
+    universes: Mutex<HashMap<UniverseKey, Universe>>,
+    modes: Mutex<Modes>,
+}
+
+impl DmxMerger {
+    // Returns the new merge for the universe, or None when the packet plays no part in merging.
+    pub fn update(&self, protocol: &'static str, dmx: &DmxData) -> Option<DmxMergeData> {
+        if dmx.start_code != START_CODE_LEVELS && dmx.start_code != START_CODE_ADDRESS_PRIORITY {
+            return None;
+        }
+        let (key, name, priority, timeout, terminated) = match dmx.source {
+            // Preview data is meant for visualisers, not for the rig.
+            Some(DmxSource::Sacn(ref info)) if !info.preview => (
+                format!("sacn:{}", info.cid),
+                info.name.clone(),
+                info.priority,
+                E131_NETWORK_DATA_LOSS_TIMEOUT,
+                info.terminated,
+            ),
+            Some(DmxSource::Artnet(ref info)) => (
+                format!("artnet:{}", info.sender),
+                info.sender.clone(),
+                E131_DEFAULT_PRIORITY,
+                ARTNET_MERGE_TIMEOUT,
+                false,
+            ),
+            _ => return None,
+        };
+
+        let universe_key = (protocol, dmx.universe);
+        let mut universes = self.universes.lock().unwrap();
+        let universe = universes.entry(universe_key).or_default();
+        if terminated {
+            universe.sources.remove(&key);
+        } else {
+            universe.counter += 1;
+            let counter = universe.counter;
+            let source = universe.sources.entry(key).or_insert_with(|| Source::new(timeout));
+            source.name = name;
+            source.priority = priority;
+            source.last_seen = Local::now().format("%H:%M:%S%.3f").to_string();
+            source.last_seen_at = Instant::now();
+            if dmx.start_code == START_CODE_ADDRESS_PRIORITY {
+                source.address_priorities = Some(dmx.channels[..dmx.channels.len().min(UNIVERSE_SIZE)].to_vec());
+                source.address_priorities_at = Some(Instant::now());
+            } else {
+                source.set_levels(&dmx.channels, counter);
+            }
+        }
+
+        let mode = self.modes.lock().unwrap().get(&universe_key);
+        Some(merge(universe_key, universe, mode))
+    }
+
+    // Drops sources that have gone quiet, and per-address priority that has stopped
+    // arriving, returning the new merge of every universe that changed.
+    pub fn expire(&self) -> Vec<DmxMergeData> {
+        let mut universes = self.universes.lock().unwrap();
+        let modes = self.modes.lock().unwrap();
+        let mut merged = Vec::new();
+        for (key, universe) in universes.iter_mut() {
+            let before = universe.sources.len();
+            universe.sources.retain(|_, s| s.last_seen_at.elapsed() < s.timeout);
+            let mut changed = universe.sources.len() != before;
+            for source in universe.sources.values_mut() {
+                if source.address_priorities_at.is_some_and(|t| t.elapsed() >= E131_NETWORK_DATA_LOSS_TIMEOUT) {
+                    source.address_priorities = None;
+                    source.address_priorities_at = None;
+                    changed = true;
+                }
+            }
+            if changed {
+                merged.push(merge(*key, universe, modes.get(key)));
+            }
+        }
+        universes.retain(|_, u| !u.sources.is_empty());
+        merged
+    }
+
+    // Without a universe the mode becomes the default for every universe without its own.
+    pub fn set_mode(&self, universe: Option<(&'static str, u16)>, mode: DmxMergeMode) {
+        let mut modes = self.modes.lock().unwrap();
+        match universe {
+            Some(key) => {
+                modes.universes.insert(key, mode);
+            }
+            None => modes.default = mode,
+        }
+    }
+
+    pub fn snapshot(&self) -> Vec<DmxMergeData> {
+        let universes = self.universes.lock().unwrap();
+        let modes = self.modes.lock().unwrap();
+        let mut merged: Vec<DmxMergeData> = universes.iter()
+            .map(|(key, universe)| merge(*key, universe, modes.get(key)))
+            .collect();
+        merged.sort_by(|a, b| a.protocol.cmp(&b.protocol).then(a.universe.cmp(&b.universe)));
+        merged
+    }
+}
+
+pub fn protocol(name: &str) -> Result<&'static str, String> {
+    match name {
+        "sacn" => Ok("sacn"),
+        "artnet" => Ok("artnet"),
+        other => Err(format!("Unknown merge protocol {}", other)),
+    }
+}
+
+fn merge((protocol, number): UniverseKey, universe: &Universe, mode: DmxMergeMode) -> DmxMergeData {
+    let sources: Vec<(&String, &Source)> = universe.sources.iter().collect();
+    let mut levels = vec![0u8; UNIVERSE_SIZE];
+    let mut owners = vec![None; UNIVERSE_SIZE];
+    let mut channels_won = vec![0usize; sources.len()];
+
+    for channel in 0..UNIVERSE_SIZE {
+        let mut best: Option<(usize, u8)> = None;
+        for (i, (_, source)) in sources.iter().enumerate() {
+            let Some(priority) = source.priority_at(channel) else {
+                continue;
+            };
+            let wins = match best {
+                None => true,
+                Some((b, best_priority)) => {
+                    let current = sources[b].1;
+                    priority > best_priority || (priority == best_priority && match mode {
+                        DmxMergeMode::Htp => source.levels[channel] > current.levels[channel],
+                        DmxMergeMode::Ltp => source.changed[channel] > current.changed[channel]
+                            || (source.changed[channel] == current.changed[channel] && source.levels[channel] > current.levels[channel]),
+                    })
+                }
+            };
+            if wins {
+                best = Some((i, priority));
+            }
+        }
+        if let Some((i, _)) = best {
+            levels[channel] = sources[i].1.levels[channel];
+            owners[channel] = Some(i);
+            channels_won[i] += 1;
+        }
+    }
+
+    DmxMergeData {
+        protocol: protocol.to_string(),
+        universe: number,
+        mode,
+        levels,
+        owners,
+        sources: sources.iter().zip(channels_won)
+            .map(|((key, s), won)| DmxMergeSource {
+                key: (*key).clone(),
+                name: s.name.clone(),
+                priority: s.priority,
+                address_priorities: s.address_priorities.clone(),
+                levels: s.levels.clone(),
+                channels_won: won,
+                last_seen: s.last_seen.clone(),
+            })
+            .collect(),
+    }
+}
@@ -0,0 +1,217 @@
+use std::collections::BTreeMap;
+use std::net::Ipv4Addr;
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+use sacn::packet::{E131_DEFAULT_PRIORITY, E131_MAX_PRIORITY};
+use uuid::Uuid;
+
+use crate::dmx_sender::{ArtnetSender, SacnSender};
+use crate::protocols::{DmxOutputCommand, DmxOutputConfig, DmxOutputUniverse};
+
+pub const UNIVERSE_SIZE: usize = 512;
+const MAX_SACN_UNIVERSE: u16 = 63999;
+// Art-Net universes are 15-bit port addresses.
+const MAX_ARTNET_UNIVERSE: u16 = 0x7FFF;
+
+// Level changes are sent straight away rather than on the next refresh, but no faster
+// than the 44 Hz a full DMX512 frame allows so fader moves don't flood the network.
+const MIN_FRAME_INTERVAL: Duration = Duration::from_micros(22_700);
+const DEFAULT_REFRESH: Duration = Duration::from_secs(1);
+// Keep-alives have to land well inside the 2.5 s E1.31 data loss timeout, and
+// can't usefully come faster than the DMX512 frame rate.
+const MIN_REFRESH_RATE: f64 = 0.5;
+const MAX_REFRESH_RATE: f64 = 44.0;
+
+// Levels being transmitted, keyed by universe.
+pub type LevelTable = Arc<Mutex<BTreeMap<u16, [u8; UNIVERSE_SIZE]>>>;
+
+struct Outputs {
+    sacn: Option<SacnSender>,
+    sacn_priority: u8,
+    sacn_unicast: Option<Ipv4Addr>,
+    artnet: Option<(ArtnetSender, Ipv4Addr)>,
+}
+
+impl Outputs {
+    fn none() -> Outputs {
+        Outputs {
+            sacn: None,
+            sacn_priority: E131_DEFAULT_PRIORITY,
+            sacn_unicast: None,
+            artnet: None,
+        }
+    }
+
+    fn open(config: &DmxOutputConfig) -> Outputs {
+        let mut outputs = Outputs::none();
+
+        if config.sacn_enabled {
+            let cid = match config.sacn_cid.as_deref().map(Uuid::parse_str) {
+                Some(Ok(cid)) => Some(cid),
+                Some(Err(e)) => {
+                    eprintln!("Invalid sACN CID, using a random one: {}", e);
+                    None
+                }
+                None => None,
+            };
+            match SacnSender::new(&config.sacn_source_name, cid) {
+                Ok(s) => outputs.sacn = Some(s),
+                Err(e) => eprintln!("Failed to open sACN output: {}", e),
+            }
+            outputs.sacn_priority = config.sacn_priority.min(E131_MAX_PRIORITY);
+            outputs.sacn_unicast = config.sacn_unicast.as_deref().and_then(|ip| ip.parse().ok());
+        }
+
+        if let Some(ref target) = config.artnet_target {
+            match (ArtnetSender::new(), target.parse::<Ipv4Addr>()) {
+                (Ok(s), Ok(ip)) => outputs.artnet = Some((s, ip)),
+                (Err(e), _) => eprintln!("Failed to open Art-Net output: {}", e),
+                (_, Err(e)) => eprintln!("Invalid Art-Net output target {}: {}", target, e),
+            }
+        }
+
+        outputs
+    }
+
+    // Each universe only goes out on the protocols that can carry its number.
+    fn send(&mut self, universe: u16, levels: &[u8]) {
+        if let Some(sender) = self.sacn.as_mut().filter(|_| check_sacn_universe(universe).is_ok()) {
+            if let Err(e) = sender.send(universe, levels, Some(self.sacn_priority), self.sacn_unicast) {
+                eprintln!("Failed to send sACN universe {}: {}", universe, e);
+            }
+        }
+        if let Some((sender, destination)) = self.artnet.as_mut().filter(|_| check_artnet_universe(universe).is_ok()) {
+            if let Err(e) = sender.send(universe, levels, *destination) {
+                eprintln!("Failed to send Art-Net universe {}: {}", universe, e);
+            }
+        }
+    }
+
+    fn release(&mut self, universe: u16) {
+        if let Some(sender) = self.sacn.as_mut().filter(|_| check_sacn_universe(universe).is_ok()) {
+            if let Err(e) = sender.terminate(universe) {
+                eprintln!("Failed to terminate sACN universe {}: {}", universe, e);
+            }
+        }
+    }
+}
+
+pub fn start(command_rx: mpsc::Receiver<DmxOutputCommand>, levels: LevelTable) {
+    let mut outputs = Outputs::none();
+    let mut refresh = DEFAULT_REFRESH;
+    let mut next_frame = Instant::now() + refresh;
+    let mut last_frame = Instant::now();
+
+    loop {
+        let mut changed = false;
+        match command_rx.recv_timeout(next_frame.saturating_duration_since(Instant::now())) {
+            Ok(DmxOutputCommand::Configure(config)) => {
+                for &universe in levels.lock().unwrap().keys() {
+                    outputs.release(universe);
+                }
+                outputs = Outputs::open(&config);
+                if let Some(interval) = refresh_interval(config.refresh_rate) {
+                    refresh = interval;
+                }
+                changed = true;
+            }
+            Ok(DmxOutputCommand::SetChannel { universe, channel, value }) => {
+                let mut table = levels.lock().unwrap();
+                table.entry(universe).or_insert([0; UNIVERSE_SIZE])[channel as usize - 1] = value;
+                changed = true;
+            }
+            Ok(DmxOutputCommand::SetRange { universe, start, end, value }) => {
+                let mut table = levels.lock().unwrap();
+                table.entry(universe).or_insert([0; UNIVERSE_SIZE])[start as usize - 1..end as usize].fill(value);
+                changed = true;
+            }
+            Ok(DmxOutputCommand::SetUniverse { universe, levels: new_levels }) => {
+                let mut table = levels.lock().unwrap();
+                let universe_levels = table.entry(universe).or_insert([0; UNIVERSE_SIZE]);
+                let count = new_levels.len().min(UNIVERSE_SIZE);
+                universe_levels[..count].copy_from_slice(&new_levels[..count]);
+                universe_levels[count..].fill(0);
+                changed = true;
+            }
+            Ok(DmxOutputCommand::ReleaseUniverse(universe)) => {
+                if levels.lock().unwrap().remove(&universe).is_some() {
+                    outputs.release(universe);
+                }
+            }
+            Ok(DmxOutputCommand::Stop) => {
+                let mut table = levels.lock().unwrap();
+                for &universe in table.keys() {
+                    outputs.release(universe);
+                }
+                table.clear();
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => return,
+        }
+
+        if changed {
+            next_frame = next_frame.min(last_frame + MIN_FRAME_INTERVAL);
+        }
+
+        let now = Instant::now();
+        if now >= next_frame {
+            for (&universe, universe_levels) in levels.lock().unwrap().iter() {
+                outputs.send(universe, universe_levels);
+            }
+            last_frame = now;
+            next_frame = now + refresh;
+        }
+    }
+}
+
+// A rate of 0 keeps the current refresh.
+pub fn check_refresh_rate(rate: f64) -> Result<(), String> {
+    if rate != 0.0 && !(MIN_REFRESH_RATE..=MAX_REFRESH_RATE).contains(&rate) {
+        return Err(format!("Refresh rate must be {}-{} Hz", MIN_REFRESH_RATE, MAX_REFRESH_RATE));
+    }
+    Ok(())
+}
+
+// Clamped as well as checked, so a rate that slipped past validation can't panic.
+pub fn refresh_interval(rate: f64) -> Option<Duration> {
+    (rate > 0.0).then(|| Duration::from_secs_f64(1.0 / rate.clamp(MIN_REFRESH_RATE, MAX_REFRESH_RATE)))
+}
+
+pub fn check_channel(channel: u16) -> Result<(), String> {
+    if channel == 0 || channel as usize > UNIVERSE_SIZE {
+        return Err(format!("Channel {} is outside 1-{}", channel, UNIVERSE_SIZE));
+    }
+    Ok(())
+}
+
+// Output universes are sent on sACN and Art-Net alike, so a number either one can
+// carry is accepted.
+pub fn check_universe(universe: u16) -> Result<(), String> {
+    if universe > MAX_SACN_UNIVERSE {
+        return Err(format!("Universe {} is outside 0-{}", universe, MAX_SACN_UNIVERSE));
+    }
+    Ok(())
+}
+
+pub fn check_sacn_universe(universe: u16) -> Result<(), String> {
+    if universe == 0 || universe > MAX_SACN_UNIVERSE {
+        return Err(format!("sACN universe {} is outside 1-{}", universe, MAX_SACN_UNIVERSE));
+    }
+    Ok(())
+}
+
+pub fn check_artnet_universe(universe: u16) -> Result<(), String> {
+    if universe > MAX_ARTNET_UNIVERSE {
+        return Err(format!("Art-Net universe {} is outside 0-{}", universe, MAX_ARTNET_UNIVERSE));
+    }
+    Ok(())
+}
+
+pub fn list_universes(levels: &LevelTable) -> Vec<DmxOutputUniverse> {
+    levels.lock().unwrap().iter()
+        .map(|(&universe, universe_levels)| DmxOutputUniverse {
+            universe,
+            levels: universe_levels.to_vec(),
+        })
+        .collect()
+}
@@ -0,0 +1,100 @@
+use std::collections::{HashMap, HashSet};
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+use artnet_protocol::{ArtCommand, Output, PortAddress};
+use sacn::packet::E131_DEFAULT_PRIORITY;
+use sacn::source::SacnSource;
+use uuid::Uuid;
+
+use crate::protocols::START_CODE_LEVELS;
+
+pub struct SacnSender {
+    source: SacnSource,
+    registered: HashSet<u16>,
+}
+
+impl SacnSender {
+    // Binds to an ephemeral port so the sender never competes with the listener for
+    // unicast traffic arriving on 5568. A random CID is generated when none is given.
+    pub fn new(name: &str, cid: Option<Uuid>) -> Result<SacnSender, String> {
+        let addr = SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), 0);
+        let source = match cid {
+            Some(cid) => SacnSource::with_cid_ip(name, cid, addr),
+            None => SacnSource::with_ip(name, addr),
+        }.map_err(|e| e.to_string())?;
+        Ok(SacnSender {
+            source,
+            registered: HashSet::new(),
+        })
+    }
+
+    // `channels` excludes the start code; `destination` of None sends multicast.
+    pub fn send(&mut self, universe: u16, channels: &[u8], priority: Option<u8>, destination: Option<Ipv4Addr>) -> Result<(), String> {
+        self.send_start_code(universe, START_CODE_LEVELS, channels, priority, destination)
+    }
+
+    pub fn send_start_code(&mut self, universe: u16, start_code: u8, channels: &[u8], priority: Option<u8>, destination: Option<Ipv4Addr>) -> Result<(), String> {
+        if !self.registered.contains(&universe) {
+            self.source.register_universe(universe).map_err(|e| e.to_string())?;
+            self.registered.insert(universe);
+        }
+
+        let mut data = Vec::with_capacity(channels.len().min(512) + 1);
+        data.push(start_code);
+        data.extend_from_slice(&channels[..channels.len().min(512)]);
+
+        let dst = destination.map(|ip| SocketAddr::new(ip.into(), sacn::packet::ACN_SDT_MULTICAST_PORT));
+        self.source
+            .send(&[universe], &data, Some(priority.unwrap_or(E131_DEFAULT_PRIORITY)), dst, None)
+            .map_err(|e| e.to_string())
+    }
+
+    // Sends the E1.31 stream terminated packets so receivers drop this source straight away.
+    pub fn terminate(&mut self, universe: u16) -> Result<(), String> {
+        if !self.registered.remove(&universe) {
+            return Ok(());
+        }
+        self.source.terminate_stream(universe, START_CODE_LEVELS).map_err(|e| e.to_string())
+    }
+}
+
+pub struct ArtnetSender {
+    socket: UdpSocket,
+    sequences: HashMap<u16, u8>,
+}
+
+impl ArtnetSender {
+    pub fn new() -> Result<ArtnetSender, String> {
+        let socket = UdpSocket::bind("0.0.0.0:0").map_err(|e| e.to_string())?;
+        socket.set_broadcast(true).map_err(|e| e.to_string())?;
+        Ok(ArtnetSender {
+            socket,
+            sequences: HashMap::new(),
+        })
+    }
+
+    // `destination` may be a node's unicast address or a broadcast address.
+    pub fn send(&mut self, universe: u16, channels: &[u8], destination: Ipv4Addr) -> Result<(), String> {
+        let port_address = PortAddress::try_from(universe).map_err(|e| e.to_string())?;
+
+        // Sequence 0 means "not used", so valid sequence numbers wrap from 0xFF back to 0x01.
+        let sequence = self.sequences.entry(universe).or_insert(0);
+        *sequence = if *sequence == 0xFF { 1 } else { *sequence + 1 };
+
+        let mut data = channels[..channels.len().min(512)].to_vec();
+        if data.len() < 2 {
+            data.resize(2, 0);
+        }
+
+        let command = ArtCommand::Output(Output {
+            sequence: *sequence,
+            port_address,
+            data: data.into(),
+            ..Output::default()
+        });
+        let bytes = command.write_to_buffer().map_err(|e| e.to_string())?;
+        self.socket
+            .send_to(&bytes, SocketAddr::new(destination.into(), 6454))
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+}
@@ -0,0 +1,222 @@
+use std::io::Read;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use crate::protocols::{DmxData, DmxSource, EnttecCommand, EnttecSourceInfo, EnttecWidgetInfo, START_CODE_LEVELS};
+
+// Enttec DMX USB Pro widget API. Every message is framed as
+// 0x7E, label, data length (LSB, MSB), data, 0xE7.
+const START_OF_MESSAGE: u8 = 0x7E;
+const END_OF_MESSAGE: u8 = 0xE7;
+const LABEL_GET_PARAMETERS: u8 = 3;
+const LABEL_RECEIVED_DMX: u8 = 5;
+const LABEL_RECEIVE_ON_CHANGE: u8 = 8;
+const LABEL_CHANGE_OF_STATE: u8 = 9;
+const LABEL_GET_SERIAL: u8 = 10;
+
+const MAX_MESSAGE_LENGTH: usize = 600;
+// Break and mark-after-break times are reported in units of 10.67 µs.
+const TIME_UNIT_US: f64 = 10.67;
+
+struct Widget {
+    port: Box<dyn serialport::SerialPort>,
+    port_name: String,
+    universe: u16,
+    serial_number: String,
+    buffer: Vec<u8>,
+    // Start code followed by 512 channels, kept up to date by change-of-state messages.
+    levels: [u8; 513],
+}
+
+pub fn start(tx: mpsc::Sender<DmxData>, command_rx: mpsc::Receiver<EnttecCommand>, widget_tx: mpsc::Sender<EnttecWidgetInfo>) {
+    let mut widget: Option<Widget> = None;
+    let mut buf = [0u8; 1024];
+
+    loop {
+        match command_rx.try_recv() {
+            Ok(EnttecCommand::Start { port, universe, on_change }) => {
+                widget = match open_widget(&port, universe, on_change) {
+                    Ok(w) => {
+                        eprintln!("Enttec widget opened on {} as universe {}", port, universe);
+                        Some(w)
+                    }
+                    Err(e) => {
+                        eprintln!("Failed to open Enttec widget on {}: {}", port, e);
+                        None
+                    }
+                };
+            }
+            Ok(EnttecCommand::Stop) => {
+                widget = None;
+                eprintln!("Enttec widget closed");
+            }
+            // The widget failed or was closed and nothing can reopen it.
+            Err(mpsc::TryRecvError::Disconnected) if widget.is_none() => return,
+            Err(_) => {}
+        }
+
+        if let Some(ref mut w) = widget {
+            match w.port.read(&mut buf) {
+                Ok(bytes_read) if bytes_read > 0 => {
+                    w.buffer.extend_from_slice(&buf[..bytes_read]);
+                    while let Some((label, data)) = next_message(&mut w.buffer) {
+                        handle_message(w, label, &data, &tx, &widget_tx);
+                    }
+                }
+                Ok(_) => {}
+                Err(ref e) if e.kind() == std::io::ErrorKind::TimedOut => {}
+                Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {}
+                Err(e) => {
+                    eprintln!("Enttec widget read error: {}", e);
+                }
+            }
+        } else {
+            std::thread::sleep(Duration::from_millis(100));
+        }
+    }
+}
+
+fn open_widget(port_name: &str, universe: u16, on_change: bool) -> Result<Widget, String> {
+    // The widget is a USB device behind an FTDI bridge, so the baud rate is ignored.
+    let mut port = serialport::new(port_name, 57600)
+        .timeout(Duration::from_millis(100))
+        .open()
+        .map_err(|e| e.to_string())?;
+
+    // Replies arrive in order, so the serial number is known by the time the parameters are reported.
+    send_message(port.as_mut(), LABEL_GET_SERIAL, &[])?;
+    send_message(port.as_mut(), LABEL_GET_PARAMETERS, &[0, 0])?;
+    send_message(port.as_mut(), LABEL_RECEIVE_ON_CHANGE, &[on_change as u8])?;
+
+    Ok(Widget {
+        port,
+        port_name: port_name.to_string(),
+        universe,
+        serial_number: String::new(),
+        buffer: Vec::new(),
+        levels: [0; 513],
+    })
+}
+
+fn send_message(port: &mut dyn serialport::SerialPort, label: u8, data: &[u8]) -> Result<(), String> {
+    let length = (data.len() as u16).to_le_bytes();
+    let mut message = Vec::with_capacity(data.len() + 5);
+    message.extend_from_slice(&[START_OF_MESSAGE, label, length[0], length[1]]);
+    message.extend_from_slice(data);
+    message.push(END_OF_MESSAGE);
+    port.write_all(&message).map_err(|e| e.to_string())
+}
+
+// Pops the next complete message off `buffer`, skipping anything that isn't validly framed.
+fn next_message(buffer: &mut Vec<u8>) -> Option<(u8, Vec<u8>)> {
+    loop {
+        match buffer.iter().position(|&b| b == START_OF_MESSAGE) {
+            Some(start) => {
+                buffer.drain(..start);
+            }
+            None => {
+                buffer.clear();
+                return None;
+            }
+        }
+        if buffer.len() < 4 {
+            return None;
+        }
+
+        let length = u16::from_le_bytes([buffer[2], buffer[3]]) as usize;
+        if length > MAX_MESSAGE_LENGTH {
+            buffer.remove(0);
+            continue;
+        }
+        if buffer.len() < length + 5 {
+            return None;
+        }
+        if buffer[length + 4] != END_OF_MESSAGE {
+            buffer.remove(0);
+            continue;
+        }
+
+        let label = buffer[1];
+        let data = buffer[4..length + 4].to_vec();
+        buffer.drain(..length + 5);
+        return Some((label, data));
+    }
+}
+
+fn handle_message(
+    widget: &mut Widget,
+    label: u8,
+    data: &[u8],
+    tx: &mpsc::Sender<DmxData>,
+    widget_tx: &mpsc::Sender<EnttecWidgetInfo>,
+) {
+    match label {
+        LABEL_RECEIVED_DMX => {
+            // First byte is the receive status; bit 0 is queue overflow, bit 1 is overrun.
+            let Some((&status, frame)) = data.split_first() else {
+                return;
+            };
+            if status != 0 {
+                eprintln!("Enttec widget receive error on {} (status {:#04x})", widget.port_name, status);
+            }
+            // Only null start code packets carry levels.
+            if frame.first() != Some(&START_CODE_LEVELS) {
+                return;
+            }
+            let count = frame.len().min(widget.levels.len());
+            widget.levels[..count].copy_from_slice(&frame[..count]);
+            widget.levels[count..].fill(0);
+            send_levels(widget, tx);
+        }
+        LABEL_CHANGE_OF_STATE => {
+            // Byte 0 is the block start (in units of 8 slots), bytes 1-5 a 40 bit
+            // mask of changed slots, followed by one byte per changed slot.
+            if data.len() < 6 {
+                return;
+            }
+            let first_slot = data[0] as usize * 8;
+            let mut values = data[6..].iter();
+            for bit in 0..40 {
+                if data[1 + bit / 8] & (1 << (bit % 8)) == 0 {
+                    continue;
+                }
+                let Some(&value) = values.next() else {
+                    break;
+                };
+                if let Some(level) = widget.levels.get_mut(first_slot + bit) {
+                    *level = value;
+                }
+            }
+            if widget.levels[0] == START_CODE_LEVELS {
+                send_levels(widget, tx);
+            }
+        }
+        LABEL_GET_PARAMETERS if data.len() >= 5 => {
+            widget_tx.send(EnttecWidgetInfo {
+                port: widget.port_name.clone(),
+                serial_number: widget.serial_number.clone(),
+                firmware: format!("{}.{}", data[1], data[0]),
+                break_time_us: data[2] as f64 * TIME_UNIT_US,
+                mab_time_us: data[3] as f64 * TIME_UNIT_US,
+                refresh_rate: data[4],
+            }).ok();
+        }
+        LABEL_GET_SERIAL if data.len() >= 4 => {
+            // Four BCD bytes, least significant first.
+            widget.serial_number = data[..4].iter().rev().map(|b| format!("{:02X}", b)).collect();
+        }
+        _ => {}
+    }
+}
+
+fn send_levels(widget: &Widget, tx: &mpsc::Sender<DmxData>) {
+    tx.send(DmxData {
+        universe: widget.universe,
+        channels: widget.levels[1..].to_vec(),
+        source: Some(DmxSource::Enttec(EnttecSourceInfo {
+            port: widget.port_name.clone(),
+            serial_number: widget.serial_number.clone(),
+        })),
+        start_code: START_CODE_LEVELS,
+    }).ok();
+}
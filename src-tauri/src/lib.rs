@@ -1,23 +1,67 @@
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
+use std::sync::Arc;
 use tauri::{Emitter, Manager};
 mod protocols;
 mod osc_listener;
+mod osc_filter;
+mod osc_proxy;
+mod midi_listener;
+mod midi_parser;
+mod msc;
+mod mtc;
 mod sacn_listener;
+mod sacn_discovery;
 mod osc_message_data;
 mod serial_listener;
+mod serial_payload;
+mod serial_framing;
 mod artnet_listener;
+mod artnet_discovery;
+mod enttec_listener;
+mod ltc_decoder;
+mod recorder;
+mod dmx_sender;
+mod player;
+mod stats;
+mod timecode;
+mod cli;
+mod osc_sender;
+mod dmx_output;
+mod dmx_bridge;
+mod dmx_merge;
 
 struct AppState {
     osc_cmd_tx: std::sync::mpsc::Sender<protocols::OscCommand>,
+    osc_connections: osc_listener::ConnectionTable,
+    osc_filter: Arc<osc_filter::OscFilter>,
+    osc_proxy: Arc<osc_proxy::OscProxy>,
     sacn_cmd_tx: std::sync::mpsc::Sender<protocols::SacnCommand>,
     serial_cmd_tx: std::sync::mpsc::Sender<protocols::SerialCommand>,
+    serial_port: serial_listener::OpenPort,
+    midi_cmd_tx: std::sync::mpsc::Sender<protocols::MidiCommand>,
     artnet_cmd_tx: std::sync::mpsc::Sender<protocols::ArtnetCommand>,
+    enttec_cmd_tx: std::sync::mpsc::Sender<protocols::EnttecCommand>,
+    ltc_cmd_tx: std::sync::mpsc::Sender<protocols::LtcCommand>,
+    sacn_sources: sacn_listener::SourceTable,
+    sacn_discovered: sacn_discovery::DiscoveryTable,
+    artnet_nodes: artnet_discovery::NodeTable,
+    recorder: Arc<recorder::Recorder>,
+    player_cmd_tx: std::sync::mpsc::Sender<protocols::PlayerCommand>,
+    stats: Arc<stats::Stats>,
+    timecode_tracker: Arc<timecode::TimecodeTracker>,
+    osc_sender: osc_sender::OscSender,
+    osc_presets: osc_sender::PresetStore,
+    dmx_output_cmd_tx: std::sync::mpsc::Sender<protocols::DmxOutputCommand>,
+    dmx_output_levels: dmx_output::LevelTable,
+    dmx_bridge_cmd_tx: std::sync::mpsc::Sender<protocols::DmxBridgeCommand>,
+    dmx_bridge_routes: dmx_bridge::RouteTable,
+    dmx_merger: Arc<dmx_merge::DmxMerger>,
 }
 
 #[tauri::command]
-fn osc_start_listener(ip: String, port: u16, state: tauri::State<'_, AppState>) -> Result<(), String>{
+fn osc_start_listener(ip: String, port: u16, transport: Option<protocols::OscTransport>, state: tauri::State<'_, AppState>) -> Result<(), String>{
     state.osc_cmd_tx
-        .send(protocols::OscCommand::Start { ip, port })
+        .send(protocols::OscCommand::Start { ip, port, transport: transport.unwrap_or_default() })
         .map_err(|e| e.to_string())
 }
 
@@ -28,6 +72,66 @@ fn osc_stop_listener(state: tauri::State<'_, AppState>) -> Result<(), String> {
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+fn osc_list_connections(state: tauri::State<'_, AppState>) -> Vec<protocols::OscConnection> {
+    osc_listener::list_connections(&state.osc_connections)
+}
+
+#[tauri::command]
+fn osc_set_filters(rules: Vec<protocols::OscFilterRule>, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    state.osc_filter.set_rules(rules)
+}
+
+#[tauri::command]
+fn osc_list_filters(state: tauri::State<'_, AppState>) -> Vec<protocols::OscFilterRule> {
+    state.osc_filter.rules()
+}
+
+#[tauri::command]
+fn osc_set_proxy(config: Option<protocols::OscProxyConfig>, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    state.osc_proxy.configure(config)
+}
+
+#[tauri::command]
+fn osc_get_proxy(state: tauri::State<'_, AppState>) -> Option<protocols::OscProxyConfig> {
+    state.osc_proxy.config()
+}
+
+#[tauri::command]
+fn osc_send(target: String, packet: osc_message_data::OscPacketData, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    state.osc_sender.send(&target, &packet)
+}
+
+#[tauri::command]
+fn osc_start_repeat(id: String, target: String, packet: osc_message_data::OscPacketData, interval_ms: u64, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    state.osc_sender.start_repeat(id, &target, packet, std::time::Duration::from_millis(interval_ms))
+}
+
+#[tauri::command]
+fn osc_stop_repeat(id: String, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    state.osc_sender.stop_repeat(&id)
+}
+
+#[tauri::command]
+fn osc_list_repeats(state: tauri::State<'_, AppState>) -> Vec<String> {
+    state.osc_sender.list_repeats()
+}
+
+#[tauri::command]
+fn osc_list_presets(state: tauri::State<'_, AppState>) -> Vec<protocols::OscPreset> {
+    state.osc_presets.list()
+}
+
+#[tauri::command]
+fn osc_save_preset(preset: protocols::OscPreset, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    state.osc_presets.save(preset)
+}
+
+#[tauri::command]
+fn osc_delete_preset(name: String, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    state.osc_presets.delete(&name)
+}
+
 #[tauri::command]
 fn sacn_start_listener(ip: String, state: tauri::State<'_, AppState>) -> Result<(), String>{
     state.sacn_cmd_tx
@@ -57,9 +161,40 @@ fn sacn_unsubscribe_universe(universe: u16, state: tauri::State<'_, AppState>) -
 }
 
 #[tauri::command]
-fn serial_start_listener(port: String, baud_rate: u32, state: tauri::State<'_, AppState>) -> Result<(), String> {
+fn sacn_list_sources(state: tauri::State<'_, AppState>) -> Vec<protocols::SacnUniverseSources> {
+    sacn_listener::active_sources(&state.sacn_sources)
+}
+
+#[tauri::command]
+fn sacn_list_discovered_universes(state: tauri::State<'_, AppState>) -> Vec<protocols::SacnDiscoveredSource> {
+    sacn_discovery::list_sources(&state.sacn_discovered)
+}
+
+#[tauri::command]
+fn sacn_subscribe_discovered(state: tauri::State<'_, AppState>) -> Result<(), String> {
+    state.sacn_cmd_tx
+        .send(protocols::SacnCommand::SubscribeDiscovered)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn serial_start_listener(port: String, baud_rate: u32, config: Option<protocols::SerialLineConfig>, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    state.serial_cmd_tx
+        .send(protocols::SerialCommand::Start { port, baud_rate, config: config.unwrap_or_default() })
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn serial_set_dtr(level: bool, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    state.serial_cmd_tx
+        .send(protocols::SerialCommand::SetDtr(level))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn serial_set_rts(level: bool, state: tauri::State<'_, AppState>) -> Result<(), String> {
     state.serial_cmd_tx
-        .send(protocols::SerialCommand::Start { port, baud_rate })
+        .send(protocols::SerialCommand::SetRts(level))
         .map_err(|e| e.to_string())
 }
 
@@ -70,11 +205,74 @@ fn serial_stop_listener(state: tauri::State<'_, AppState>) -> Result<(), String>
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+fn serial_set_framing(framing: protocols::SerialFraming, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    serial_framing::validate(&framing)?;
+    state.serial_cmd_tx
+        .send(protocols::SerialCommand::SetFraming(framing))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn serial_write(
+    data: String,
+    format: protocols::SerialPayloadFormat,
+    line_ending: protocols::LineEnding,
+    repeat: Option<u32>,
+    interval_ms: Option<u64>,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), String> {
+    if state.serial_port.lock().unwrap().is_none() {
+        return Err("No serial port is open".to_string());
+    }
+    let bytes = serial_payload::parse(&data, format, line_ending)?;
+    state.serial_cmd_tx
+        .send(protocols::SerialCommand::Write {
+            bytes,
+            count: repeat.unwrap_or(1).max(1),
+            interval: std::time::Duration::from_millis(interval_ms.unwrap_or(0)),
+        })
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn serial_set_midi_decoding(enabled: bool, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    state.serial_cmd_tx
+        .send(protocols::SerialCommand::SetMidiDecoding(enabled))
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 fn serial_list_ports() -> Vec<protocols::SerialPortInfo> {
     serial_listener::list_ports()
 }
 
+#[tauri::command]
+fn midi_start_listener(port: String, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    state.midi_cmd_tx
+        .send(protocols::MidiCommand::Start { port })
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn midi_start_virtual_port(name: String, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    state.midi_cmd_tx
+        .send(protocols::MidiCommand::StartVirtual { name })
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn midi_stop_listener(state: tauri::State<'_, AppState>) -> Result<(), String> {
+    state.midi_cmd_tx
+        .send(protocols::MidiCommand::Stop)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn midi_list_ports() -> Vec<protocols::MidiPortInfo> {
+    midi_listener::list_ports()
+}
+
 #[tauri::command]
 fn artnet_start_listener(ip: String, state: tauri::State<'_, AppState>) -> Result<(), String> {
     state.artnet_cmd_tx
@@ -103,6 +301,263 @@ fn artnet_unsubscribe_universe(universe: u16, state: tauri::State<'_, AppState>)
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+fn artnet_start_discovery(broadcast_ip: String, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    state.artnet_cmd_tx
+        .send(protocols::ArtnetCommand::StartDiscovery { broadcast_ip })
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn artnet_stop_discovery(state: tauri::State<'_, AppState>) -> Result<(), String> {
+    state.artnet_cmd_tx
+        .send(protocols::ArtnetCommand::StopDiscovery)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn artnet_list_nodes(state: tauri::State<'_, AppState>) -> Vec<protocols::ArtnetNode> {
+    artnet_discovery::list_nodes(&state.artnet_nodes)
+}
+
+#[tauri::command]
+fn enttec_start_listener(port: String, universe: u16, on_change: Option<bool>, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    state.enttec_cmd_tx
+        .send(protocols::EnttecCommand::Start { port, universe, on_change: on_change.unwrap_or(false) })
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn enttec_stop_listener(state: tauri::State<'_, AppState>) -> Result<(), String> {
+    state.enttec_cmd_tx
+        .send(protocols::EnttecCommand::Stop)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn ltc_start_decoder(path: String, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    state.ltc_cmd_tx
+        .send(protocols::LtcCommand::Start { path: Some(path) })
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn ltc_stop_decoder(state: tauri::State<'_, AppState>) -> Result<(), String> {
+    state.ltc_cmd_tx
+        .send(protocols::LtcCommand::Stop)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn recorder_start(path: String, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    state.recorder.start(path)
+}
+
+#[tauri::command]
+fn recorder_stop(state: tauri::State<'_, AppState>) -> Result<(), String> {
+    state.recorder.stop()
+}
+
+#[tauri::command]
+fn recorder_status(state: tauri::State<'_, AppState>) -> protocols::RecorderStatus {
+    state.recorder.status()
+}
+
+#[tauri::command]
+fn dmx_output_configure(config: protocols::DmxOutputConfig, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    dmx_output::check_refresh_rate(config.refresh_rate)?;
+    state.dmx_output_cmd_tx
+        .send(protocols::DmxOutputCommand::Configure(config))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn dmx_output_set_channel(universe: u16, channel: u16, value: u8, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    dmx_output::check_universe(universe)?;
+    dmx_output::check_channel(channel)?;
+    state.dmx_output_cmd_tx
+        .send(protocols::DmxOutputCommand::SetChannel { universe, channel, value })
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn dmx_output_set_range(universe: u16, start: u16, end: u16, value: u8, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    dmx_output::check_universe(universe)?;
+    dmx_output::check_channel(start)?;
+    dmx_output::check_channel(end)?;
+    if start > end {
+        return Err(format!("Channel range {}-{} is reversed", start, end));
+    }
+    state.dmx_output_cmd_tx
+        .send(protocols::DmxOutputCommand::SetRange { universe, start, end, value })
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn dmx_output_set_universe(universe: u16, levels: Vec<u8>, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    dmx_output::check_universe(universe)?;
+    if levels.len() > dmx_output::UNIVERSE_SIZE {
+        return Err(format!("A universe has at most {} channels", dmx_output::UNIVERSE_SIZE));
+    }
+    state.dmx_output_cmd_tx
+        .send(protocols::DmxOutputCommand::SetUniverse { universe, levels })
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn dmx_output_release_universe(universe: u16, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    dmx_output::check_universe(universe)?;
+    state.dmx_output_cmd_tx
+        .send(protocols::DmxOutputCommand::ReleaseUniverse(universe))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn dmx_output_stop(state: tauri::State<'_, AppState>) -> Result<(), String> {
+    state.dmx_output_cmd_tx
+        .send(protocols::DmxOutputCommand::Stop)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn dmx_output_list_universes(state: tauri::State<'_, AppState>) -> Vec<protocols::DmxOutputUniverse> {
+    dmx_output::list_universes(&state.dmx_output_levels)
+}
+
+// The listeners only hand over universes they are subscribed to, so every input gets subscribed.
+#[tauri::command]
+fn dmx_bridge_configure(config: protocols::DmxBridgeConfig, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    dmx_bridge::validate(&config)?;
+    for universe in dmx_bridge::input_universes(&config, protocols::DmxBridgeDirection::ArtnetToSacn) {
+        state.artnet_cmd_tx
+            .send(protocols::ArtnetCommand::SubscribeUniverse(universe))
+            .map_err(|e| e.to_string())?;
+    }
+    for universe in dmx_bridge::input_universes(&config, protocols::DmxBridgeDirection::SacnToArtnet) {
+        state.sacn_cmd_tx
+            .send(protocols::SacnCommand::SubscribeUniverse(universe))
+            .map_err(|e| e.to_string())?;
+    }
+    state.dmx_bridge_cmd_tx
+        .send(protocols::DmxBridgeCommand::Configure(config))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn dmx_bridge_stop(state: tauri::State<'_, AppState>) -> Result<(), String> {
+    state.dmx_bridge_cmd_tx
+        .send(protocols::DmxBridgeCommand::Stop)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn dmx_bridge_list_routes(state: tauri::State<'_, AppState>) -> Vec<protocols::DmxBridgeRouteStatus> {
+    dmx_bridge::list_routes(&state.dmx_bridge_routes)
+}
+
+// Without a protocol and universe the mode becomes the default for all universes.
+#[tauri::command]
+fn dmx_merge_set_mode(mode: protocols::DmxMergeMode, protocol: Option<String>, universe: Option<u16>, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    let key = match (protocol, universe) {
+        (Some(protocol), Some(universe)) => Some((dmx_merge::protocol(&protocol)?, universe)),
+        (None, None) => None,
+        _ => return Err("Give both a protocol and a universe, or neither".to_string()),
+    };
+    state.dmx_merger.set_mode(key, mode);
+    Ok(())
+}
+
+#[tauri::command]
+fn dmx_merge_snapshot(state: tauri::State<'_, AppState>) -> Vec<protocols::DmxMergeData> {
+    state.dmx_merger.snapshot()
+}
+
+#[tauri::command]
+fn player_load(path: String, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    state.player_cmd_tx
+        .send(protocols::PlayerCommand::Load { path })
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn player_set_outputs(outputs: protocols::ReplayOutputs, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    state.player_cmd_tx
+        .send(protocols::PlayerCommand::SetOutputs(outputs))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn player_play(state: tauri::State<'_, AppState>) -> Result<(), String> {
+    state.player_cmd_tx
+        .send(protocols::PlayerCommand::Play)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn player_pause(state: tauri::State<'_, AppState>) -> Result<(), String> {
+    state.player_cmd_tx
+        .send(protocols::PlayerCommand::Pause)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn player_stop(state: tauri::State<'_, AppState>) -> Result<(), String> {
+    state.player_cmd_tx
+        .send(protocols::PlayerCommand::Stop)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn player_seek(position_ms: u64, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    state.player_cmd_tx
+        .send(protocols::PlayerCommand::Seek { position_ms })
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn player_set_speed(speed: f64, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    player::check_speed(speed)?;
+    state.player_cmd_tx
+        .send(protocols::PlayerCommand::SetSpeed(speed))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn player_set_loop(looping: bool, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    state.player_cmd_tx
+        .send(protocols::PlayerCommand::SetLoop(looping))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn stats_snapshot(state: tauri::State<'_, AppState>) -> Vec<protocols::StreamStats> {
+    state.stats.snapshot()
+}
+
+#[tauri::command]
+fn stats_reset(state: tauri::State<'_, AppState>) {
+    state.stats.reset()
+}
+
+#[tauri::command]
+fn stats_set_window(window_ms: u64, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    if window_ms == 0 {
+        return Err("Statistics window must be longer than 0 ms".to_string());
+    }
+    state.stats.set_window(std::time::Duration::from_millis(window_ms));
+    Ok(())
+}
+
+#[tauri::command]
+fn timecode_reset(state: tauri::State<'_, AppState>) {
+    state.timecode_tracker.reset()
+}
+
+// Runs a headless command when `args` (without the program name) name one.
+pub fn run_cli(args: &[String]) -> Option<i32> {
+    cli::run(args)
+}
+
 pub fn run() {
     tauri::Builder::default()
         .setup(|app| {
@@ -110,62 +565,298 @@ pub fn run() {
             let (sacn_tx, sacn_rx) = std::sync::mpsc::channel::<protocols::DmxData>();
 
             let (osc_cmd_tx, osc_cmd_rx) = std::sync::mpsc::channel::<protocols::OscCommand>();
+            let (osc_connections_tx, osc_connections_rx) = std::sync::mpsc::channel::<Vec<protocols::OscConnection>>();
             let (sacn_cmd_tx, sacn_cmd_rx) = std::sync::mpsc::channel::<protocols::SacnCommand>();
+            let (sacn_discovery_tx, sacn_discovery_rx) = std::sync::mpsc::channel::<Vec<protocols::SacnDiscoveredSource>>();
 
             let (serial_tx, serial_rx) = std::sync::mpsc::channel::<protocols::SerialData>();
             let (serial_cmd_tx, serial_cmd_rx) = std::sync::mpsc::channel::<protocols::SerialCommand>();
+            let (serial_modem_tx, serial_modem_rx) = std::sync::mpsc::channel::<protocols::SerialModemLines>();
+
+            let (midi_tx, midi_rx) = std::sync::mpsc::channel::<protocols::MidiData>();
+            let (midi_cmd_tx, midi_cmd_rx) = std::sync::mpsc::channel::<protocols::MidiCommand>();
 
             let (artnet_tx, artnet_rx) = std::sync::mpsc::channel::<protocols::DmxData>();
             let (artnet_cmd_tx, artnet_cmd_rx) = std::sync::mpsc::channel::<protocols::ArtnetCommand>();
+            let (artnet_nodes_tx, artnet_nodes_rx) = std::sync::mpsc::channel::<Vec<protocols::ArtnetNode>>();
+
+            let (timecode_tx, timecode_rx) = std::sync::mpsc::channel::<protocols::TimecodeData>();
+            let (ltc_cmd_tx, ltc_cmd_rx) = std::sync::mpsc::channel::<protocols::LtcCommand>();
+
+            let (enttec_tx, enttec_rx) = std::sync::mpsc::channel::<protocols::DmxData>();
+            let (enttec_cmd_tx, enttec_cmd_rx) = std::sync::mpsc::channel::<protocols::EnttecCommand>();
+            let (enttec_widget_tx, enttec_widget_rx) = std::sync::mpsc::channel::<protocols::EnttecWidgetInfo>();
+
+            let (player_cmd_tx, player_cmd_rx) = std::sync::mpsc::channel::<protocols::PlayerCommand>();
+            let (player_status_tx, player_status_rx) = std::sync::mpsc::channel::<protocols::PlayerStatus>();
+
+            let osc_connections = osc_listener::ConnectionTable::default();
+            let listener_connections = osc_connections.clone();
+            let osc_proxy = Arc::new(osc_proxy::OscProxy::new()?);
+            let listener_proxy = osc_proxy.clone();
+            std::thread::spawn(move || {
+                osc_listener::start(osc_tx, osc_cmd_rx, listener_connections, osc_connections_tx, listener_proxy);
+            });
+            let sacn_sources = sacn_listener::SourceTable::default();
+            let listener_sources = sacn_sources.clone();
+            let sacn_discovered = sacn_discovery::DiscoveryTable::default();
+            let listener_discovered = sacn_discovered.clone();
+            std::thread::spawn(move || {
+               sacn_listener::start(sacn_tx, sacn_cmd_rx, listener_sources, listener_discovered, sacn_discovery_tx);
+            });
 
+            let serial_port = serial_listener::OpenPort::default();
+            let listener_serial_port = serial_port.clone();
+            let serial_midi_tx = midi_tx.clone();
             std::thread::spawn(move || {
-                osc_listener::start(osc_tx, osc_cmd_rx);
+               serial_listener::start(serial_tx, serial_cmd_rx, listener_serial_port, serial_modem_tx, serial_midi_tx);
             });
+
+            std::thread::spawn(move || {
+               midi_listener::start(midi_tx, midi_cmd_rx);
+            });
+
+            let artnet_nodes = artnet_discovery::NodeTable::default();
+            let listener_nodes = artnet_nodes.clone();
+            let artnet_timecode_tx = timecode_tx.clone();
+            std::thread::spawn(move || {
+               artnet_listener::start(artnet_tx, artnet_cmd_rx, listener_nodes, artnet_nodes_tx, artnet_timecode_tx);
+            });
+
+            let ltc_timecode_tx = timecode_tx.clone();
+            std::thread::spawn(move || {
+               ltc_decoder::start(ltc_timecode_tx, ltc_cmd_rx);
+            });
+
             std::thread::spawn(move || {
-               sacn_listener::start(sacn_tx, sacn_cmd_rx);
+               enttec_listener::start(enttec_tx, enttec_cmd_rx, enttec_widget_tx);
             });
 
             std::thread::spawn(move || {
-               serial_listener::start(serial_tx, serial_cmd_rx);
+               player::start(player_cmd_rx, player_status_tx);
             });
 
+            let (dmx_output_cmd_tx, dmx_output_cmd_rx) = std::sync::mpsc::channel::<protocols::DmxOutputCommand>();
+            let dmx_output_levels = dmx_output::LevelTable::default();
+            let output_levels = dmx_output_levels.clone();
             std::thread::spawn(move || {
-               artnet_listener::start(artnet_tx, artnet_cmd_rx);
+               dmx_output::start(dmx_output_cmd_rx, output_levels);
             });
 
+            let (dmx_bridge_cmd_tx, dmx_bridge_cmd_rx) = std::sync::mpsc::channel::<protocols::DmxBridgeCommand>();
+            let dmx_bridge_routes = dmx_bridge::RouteTable::default();
+            let bridge_routes = dmx_bridge_routes.clone();
+            std::thread::spawn(move || {
+               dmx_bridge::start(dmx_bridge_cmd_rx, bridge_routes);
+            });
+
+            let recorder = Arc::new(recorder::Recorder::default());
+            let stats = Arc::new(stats::Stats::default());
+            let timecode_tracker = Arc::new(timecode::TimecodeTracker::default());
+            let dmx_merger = Arc::new(dmx_merge::DmxMerger::default());
+
             let app_handle = app.handle().clone();
+            let osc_recorder = recorder.clone();
+            let osc_stats = stats.clone();
+            let osc_filter = Arc::new(osc_filter::OscFilter::default());
+            let emit_filter = osc_filter.clone();
             std::thread::spawn(move || {
                 while let Ok(osc_data) = osc_rx.recv() {
-                    let _ = app_handle.emit("osc-message", &osc_data);
+                    // Filters only spare the UI; statistics and recordings still see everything.
+                    osc_stats.record_osc(&osc_data);
+                    osc_recorder.record(|| recorder::CaptureData::Osc(osc_data.clone()));
+                    if emit_filter.allows(&osc_data) {
+                        let _ = app_handle.emit("osc-message", &osc_data);
+                    }
+                }
+            });
+
+            let app_handle = app.handle().clone();
+            std::thread::spawn(move || {
+                while let Ok(connections) = osc_connections_rx.recv() {
+                    let _ = app_handle.emit("osc-connections", &connections);
                 }
             });
 
             let app_handle = app.handle().clone();
+            let sacn_recorder = recorder.clone();
+            let sacn_stats = stats.clone();
+            let sacn_bridge_tx = dmx_bridge_cmd_tx.clone();
+            let sacn_merger = dmx_merger.clone();
             std::thread::spawn(move || {
                 while let Ok(dmx_data) = sacn_rx.recv() {
-                    let _ = app_handle.emit("dmx-universe-data", &dmx_data);
+                    if let Some(merged) = sacn_merger.update("sacn", &dmx_data) {
+                        let _ = app_handle.emit("dmx-merge", &merged);
+                        sacn_bridge_tx.send(protocols::DmxBridgeCommand::Input(merged)).ok();
+                    }
+                    sacn_stats.record_dmx("sacn", &dmx_data);
+                    sacn_recorder.record(|| recorder::CaptureData::Sacn(dmx_data.clone()));
+                    // Per-address priority reaches the UI through the merge; RDM, text and
+                    // other alternate start codes are recorded but never shown as levels.
+                    if dmx_data.start_code == protocols::START_CODE_LEVELS {
+                        let _ = app_handle.emit("dmx-universe-data", &dmx_data);
+                    }
                 }
             });
 
             let app_handle = app.handle().clone();
+            std::thread::spawn(move || {
+                while let Ok(sources) = sacn_discovery_rx.recv() {
+                    let _ = app_handle.emit("sacn-discovered-universes", &sources);
+                }
+            });
+
+            let app_handle = app.handle().clone();
+            let serial_recorder = recorder.clone();
             std::thread::spawn(move || {
                 while let Ok(serial_data) = serial_rx.recv() {
+                    serial_recorder.record(|| recorder::CaptureData::Serial(serial_data.clone()));
                     let _ = app_handle.emit("serial-data", &serial_data);
                 }
             });
 
             let app_handle = app.handle().clone();
+            std::thread::spawn(move || {
+                while let Ok(lines) = serial_modem_rx.recv() {
+                    let _ = app_handle.emit("serial-modem-lines", &lines);
+                }
+            });
+
+            let app_handle = app.handle().clone();
+            std::thread::spawn(move || {
+                while let Ok(midi_data) = midi_rx.recv() {
+                    match midi_data.message {
+                        protocols::MidiMessage::TimecodeQuarterFrame { timecode: Some(tc), .. }
+                        | protocols::MidiMessage::TimecodeFullFrame { timecode: tc, .. } => {
+                            timecode_tx.send(timecode::timecode_data(&format!("mtc:{}", midi_data.source), tc)).ok();
+                        }
+                        _ => {}
+                    }
+                    let _ = app_handle.emit("midi-message", &midi_data);
+                }
+            });
+
+            let app_handle = app.handle().clone();
+            let forward_tracker = timecode_tracker.clone();
+            std::thread::spawn(move || {
+                while let Ok(mut timecode_data) = timecode_rx.recv() {
+                    if forward_tracker.update(&mut timecode_data) {
+                        let _ = app_handle.emit("timecode", &timecode_data);
+                    }
+                }
+            });
+
+            let app_handle = app.handle().clone();
+            let expire_tracker = timecode_tracker.clone();
+            std::thread::spawn(move || {
+                loop {
+                    std::thread::sleep(timecode::EXPIRE_INTERVAL);
+                    for timecode_data in expire_tracker.expire() {
+                        let _ = app_handle.emit("timecode", &timecode_data);
+                    }
+                }
+            });
+
+            let app_handle = app.handle().clone();
+            let artnet_recorder = recorder.clone();
+            let artnet_stats = stats.clone();
+            let artnet_bridge_tx = dmx_bridge_cmd_tx.clone();
+            let artnet_merger = dmx_merger.clone();
             std::thread::spawn(move || {
                 while let Ok(dmx_data) = artnet_rx.recv() {
+                    if let Some(merged) = artnet_merger.update("artnet", &dmx_data) {
+                        let _ = app_handle.emit("dmx-merge", &merged);
+                        artnet_bridge_tx.send(protocols::DmxBridgeCommand::Input(merged)).ok();
+                    }
+                    artnet_stats.record_dmx("artnet", &dmx_data);
+                    artnet_recorder.record(|| recorder::CaptureData::Artnet(dmx_data.clone()));
                     let _ = app_handle.emit("artnet-universe-data", &dmx_data);
                 }
             });
 
+            let app_handle = app.handle().clone();
+            std::thread::spawn(move || {
+                while let Ok(nodes) = artnet_nodes_rx.recv() {
+                    let _ = app_handle.emit("artnet-nodes", &nodes);
+                }
+            });
+
+            let app_handle = app.handle().clone();
+            let enttec_recorder = recorder.clone();
+            let enttec_stats = stats.clone();
+            std::thread::spawn(move || {
+                while let Ok(dmx_data) = enttec_rx.recv() {
+                    enttec_stats.record_dmx("enttec", &dmx_data);
+                    enttec_recorder.record(|| recorder::CaptureData::Enttec(dmx_data.clone()));
+                    let _ = app_handle.emit("enttec-universe-data", &dmx_data);
+                }
+            });
+
+            let app_handle = app.handle().clone();
+            std::thread::spawn(move || {
+                while let Ok(widget) = enttec_widget_rx.recv() {
+                    let _ = app_handle.emit("enttec-widget", &widget);
+                }
+            });
+
+            let app_handle = app.handle().clone();
+            std::thread::spawn(move || {
+                while let Ok(status) = player_status_rx.recv() {
+                    let _ = app_handle.emit("player-status", &status);
+                }
+            });
+
+            let app_handle = app.handle().clone();
+            let expire_merger = dmx_merger.clone();
+            let expire_bridge_tx = dmx_bridge_cmd_tx.clone();
+            std::thread::spawn(move || {
+                loop {
+                    std::thread::sleep(dmx_merge::EXPIRE_INTERVAL);
+                    for merged in expire_merger.expire() {
+                        let _ = app_handle.emit("dmx-merge", &merged);
+                        expire_bridge_tx.send(protocols::DmxBridgeCommand::Input(merged)).ok();
+                    }
+                }
+            });
+
+            let app_handle = app.handle().clone();
+            let emit_stats = stats.clone();
+            std::thread::spawn(move || {
+                loop {
+                    std::thread::sleep(std::time::Duration::from_secs(1));
+                    let _ = app_handle.emit("stream-stats", &emit_stats.snapshot());
+                }
+            });
+
+            let osc_sender = osc_sender::OscSender::new()?;
+            let osc_presets = osc_sender::PresetStore::load(app.path().app_config_dir()?.join("osc_presets.json"));
+
             app.manage(AppState {
                 osc_cmd_tx,
+                osc_connections,
+                osc_filter,
+                osc_proxy,
                 sacn_cmd_tx,
                 serial_cmd_tx,
+                serial_port,
+                midi_cmd_tx,
                 artnet_cmd_tx,
+                enttec_cmd_tx,
+                ltc_cmd_tx,
+                sacn_sources,
+                sacn_discovered,
+                artnet_nodes,
+                recorder,
+                player_cmd_tx,
+                stats,
+                timecode_tracker,
+                osc_sender,
+                osc_presets,
+                dmx_output_cmd_tx,
+                dmx_output_levels,
+                dmx_bridge_cmd_tx,
+                dmx_bridge_routes,
+                dmx_merger,
             });
             Ok(())
         })
@@ -174,15 +865,73 @@ pub fn run() {
             sacn_unsubscribe_universe,
             sacn_start_listener,
             sacn_stop_listener,
+            sacn_list_sources,
+            sacn_list_discovered_universes,
+            sacn_subscribe_discovered,
             osc_start_listener,
             osc_stop_listener,
+            osc_list_connections,
+            osc_set_filters,
+            osc_list_filters,
+            osc_set_proxy,
+            osc_get_proxy,
+            osc_send,
+            osc_start_repeat,
+            osc_stop_repeat,
+            osc_list_repeats,
+            osc_list_presets,
+            osc_save_preset,
+            osc_delete_preset,
             serial_start_listener,
             serial_stop_listener,
+            serial_set_dtr,
+            serial_set_rts,
+            serial_set_framing,
+            serial_write,
+            serial_set_midi_decoding,
             serial_list_ports,
+            midi_start_listener,
+            midi_start_virtual_port,
+            midi_stop_listener,
+            midi_list_ports,
             artnet_start_listener,
             artnet_stop_listener,
             artnet_subscribe_universe,
             artnet_unsubscribe_universe,
+            artnet_start_discovery,
+            artnet_stop_discovery,
+            artnet_list_nodes,
+            enttec_start_listener,
+            enttec_stop_listener,
+            ltc_start_decoder,
+            ltc_stop_decoder,
+            recorder_start,
+            recorder_stop,
+            recorder_status,
+            dmx_output_configure,
+            dmx_output_set_channel,
+            dmx_output_set_range,
+            dmx_output_set_universe,
+            dmx_output_release_universe,
+            dmx_output_stop,
+            dmx_output_list_universes,
+            dmx_bridge_configure,
+            dmx_bridge_stop,
+            dmx_bridge_list_routes,
+            dmx_merge_set_mode,
+            dmx_merge_snapshot,
+            player_load,
+            player_set_outputs,
+            player_play,
+            player_pause,
+            player_stop,
+            player_seek,
+            player_set_speed,
+            player_set_loop,
+            stats_snapshot,
+            stats_reset,
+            stats_set_window,
+            timecode_reset,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,291 @@
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use crate::protocols::{LtcCommand, Timecode, TimecodeData, TimecodeRate};
+use crate::timecode;
+
+// The last 16 of the 80 bits in an LTC frame, in the order they are sent
+// (0011 1111 1111 1101), stored least significant bit first.
+const SYNC_WORD: u128 = 0xBFFC;
+const FRAME_BITS: u32 = 80;
+// Samples decoded between checks for commands and pacing.
+const CHUNK_SAMPLES: usize = 1024;
+
+struct WavFormat {
+    sample_rate: u32,
+    channels: u16,
+    bits_per_sample: u16,
+    float: bool,
+}
+
+// Decodes the biphase mark signal one sample at a time. A transition at every
+// bit boundary plus one mid-bit for a 1 means a bit's length can be learned
+// from the signal itself, so any frame rate and playback speed locks on.
+struct LtcDecoder {
+    sample_rate: f64,
+    position: u64,
+    high: bool,
+    peak: f32,
+    last_transition: u64,
+    // Samples per bit, tracked as the signal plays.
+    period: f64,
+    half_bit: bool,
+    bits: u128,
+    last_sync: Option<u64>,
+}
+
+impl LtcDecoder {
+    fn new(sample_rate: u32) -> LtcDecoder {
+        LtcDecoder {
+            sample_rate: sample_rate as f64,
+            position: 0,
+            high: false,
+            peak: 0.0,
+            last_transition: 0,
+            // Start from 25 fps; 24 to 30 fps all lock from here.
+            period: sample_rate as f64 / (25.0 * FRAME_BITS as f64),
+            half_bit: false,
+            bits: 0,
+            last_sync: None,
+        }
+    }
+
+    // Returns the timecode of the frame starting now whenever a frame completes.
+    fn push(&mut self, sample: f32) -> Option<Timecode> {
+        self.position += 1;
+        self.peak = (self.peak * 0.999).max(sample.abs());
+        // Hysteresis around zero keeps noise from being read as transitions.
+        let threshold = self.peak * 0.2;
+        let high = if sample > threshold {
+            true
+        } else if sample < -threshold {
+            false
+        } else {
+            self.high
+        };
+        if high == self.high {
+            return None;
+        }
+        self.high = high;
+
+        let interval = (self.position - self.last_transition) as f64;
+        self.last_transition = self.position;
+        if interval > self.period * 2.5 {
+            // Silence or a dropout; wait for the signal to settle again.
+            self.half_bit = false;
+            return None;
+        }
+        if interval > self.period * 0.75 {
+            self.period = self.period * 0.75 + interval * 0.25;
+            self.half_bit = false;
+            self.push_bit(false)
+        } else {
+            self.period = self.period * 0.75 + interval * 2.0 * 0.25;
+            self.half_bit = !self.half_bit;
+            if self.half_bit {
+                return None;
+            }
+            self.push_bit(true)
+        }
+    }
+
+    fn push_bit(&mut self, bit: bool) -> Option<Timecode> {
+        self.bits = (self.bits >> 1) | (bit as u128) << (FRAME_BITS - 1);
+        if self.bits >> 64 != SYNC_WORD {
+            return None;
+        }
+        // Frame length is measured between sync words; the bit period alone is
+        // too coarse at low sample rates to tell 24 from 25 fps.
+        let frame_samples = match self.last_sync.replace(self.position) {
+            Some(last) if self.position - last < (self.period * FRAME_BITS as f64 * 1.5) as u64 => (self.position - last) as f64,
+            _ => self.period * FRAME_BITS as f64,
+        };
+        let tc = self.decode_frame(frame_samples)?;
+        // The sync word ends a frame, so the frame starting now is the next one.
+        Some(timecode::next_frame(tc))
+    }
+
+    fn decode_frame(&self, frame_samples: f64) -> Option<Timecode> {
+        let field = |start: u32, length: u32| ((self.bits >> start) & ((1 << length) - 1)) as u8;
+        let drop_frame = field(10, 1) == 1;
+        let rate = if drop_frame {
+            TimecodeRate::DropFrame
+        } else {
+            // The flag only marks drop frame, other rates come from the frame length.
+            let fps = self.sample_rate / frame_samples;
+            if fps < 24.5 {
+                TimecodeRate::Film
+            } else if fps < 27.5 {
+                TimecodeRate::Ebu
+            } else {
+                TimecodeRate::Smpte
+            }
+        };
+        let tc = Timecode {
+            frames: field(0, 4) + 10 * field(8, 2),
+            seconds: field(16, 4) + 10 * field(24, 3),
+            minutes: field(32, 4) + 10 * field(40, 3),
+            hours: field(48, 4) + 10 * field(56, 2),
+            rate,
+        };
+        let valid = tc.frames < timecode::frames_per_second(rate) && tc.seconds < 60 && tc.minutes < 60 && tc.hours < 24;
+        valid.then_some(tc)
+    }
+}
+
+pub fn start(tx: mpsc::Sender<TimecodeData>, command_rx: mpsc::Receiver<LtcCommand>) {
+    let mut next_command = command_rx.recv().ok();
+
+    while let Some(command) = next_command.take() {
+        match command {
+            LtcCommand::Start { path } => {
+                let source = match path {
+                    Some(ref p) => format!("ltc:{}", p),
+                    None => "ltc:stdin".to_string(),
+                };
+                let input: Box<dyn Read> = match path {
+                    Some(ref p) => match File::open(p) {
+                        Ok(f) => Box::new(BufReader::new(f)),
+                        Err(e) => {
+                            eprintln!("Failed to open {}: {}", p, e);
+                            next_command = command_rx.recv().ok();
+                            continue;
+                        }
+                    },
+                    None => Box::new(io::stdin()),
+                };
+                eprintln!("LTC decoder started on {}", source);
+                match decode(input, &source, &tx, &command_rx) {
+                    Ok(Some(command)) => {
+                        next_command = Some(command);
+                        continue;
+                    }
+                    Ok(None) => eprintln!("LTC decoder reached the end of {}", source),
+                    Err(e) => eprintln!("LTC decoder error on {}: {}", source, e),
+                }
+            }
+            LtcCommand::Stop => eprintln!("LTC decoder stopped"),
+        }
+        next_command = command_rx.recv().ok();
+    }
+}
+
+// Decodes until the input ends, returning any command that interrupted it.
+fn decode(
+    mut input: Box<dyn Read>,
+    source: &str,
+    tx: &mpsc::Sender<TimecodeData>,
+    command_rx: &mpsc::Receiver<LtcCommand>,
+) -> Result<Option<LtcCommand>, String> {
+    let format = read_header(&mut input)?;
+    let bytes_per_sample = format.bits_per_sample as usize / 8;
+    let frame_size = bytes_per_sample * format.channels as usize;
+    let mut decoder = LtcDecoder::new(format.sample_rate);
+    let mut buf = vec![0u8; frame_size * CHUNK_SAMPLES];
+    let started = Instant::now();
+
+    loop {
+        if let Ok(command) = command_rx.try_recv() {
+            return Ok(Some(command));
+        }
+
+        let length = read_full(&mut input, &mut buf).map_err(|e| e.to_string())?;
+        // Only the first channel is decoded; LTC is normally on a track of its own.
+        for sample in buf[..length - length % frame_size].chunks_exact(frame_size) {
+            if let Some(tc) = decoder.push(sample_value(&sample[..bytes_per_sample], &format)) {
+                wait_for_sample(started, decoder.position, format.sample_rate);
+                tx.send(timecode::timecode_data(source, tc)).ok();
+            }
+        }
+        if length < buf.len() {
+            return Ok(None);
+        }
+        wait_for_sample(started, decoder.position, format.sample_rate);
+    }
+}
+
+// Files are played at the speed they were recorded, so drift against the local
+// clock shows drift against the audio clock. Live input is never ahead and
+// doesn't wait.
+fn wait_for_sample(started: Instant, position: u64, sample_rate: u32) {
+    let due = started + Duration::from_secs_f64(position as f64 / sample_rate as f64);
+    if let Some(wait) = due.checked_duration_since(Instant::now()) {
+        std::thread::sleep(wait);
+    }
+}
+
+// Reads until `buf` is full or the input ends, since pipes return short reads.
+fn read_full(input: &mut dyn Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match input.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+// Skips to the start of the sample data. Streams written on the fly (e.g. by
+// `arecord`) leave the data length unset, so it is ignored and read to the end.
+fn read_header(input: &mut dyn Read) -> Result<WavFormat, String> {
+    let mut riff = [0u8; 12];
+    input.read_exact(&mut riff).map_err(|e| format!("Not a WAV file: {}", e))?;
+    if &riff[0..4] != b"RIFF" || &riff[8..12] != b"WAVE" {
+        return Err("Not a WAV file".to_string());
+    }
+
+    let mut format = None;
+    loop {
+        let mut header = [0u8; 8];
+        input.read_exact(&mut header).map_err(|_| "WAV file has no data chunk".to_string())?;
+        let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+        // Chunks are padded to an even length.
+        let padded = size + size % 2;
+        match &header[0..4] {
+            b"data" => break,
+            b"fmt " => {
+                let mut fmt = vec![0u8; padded];
+                input.read_exact(&mut fmt).map_err(|e| e.to_string())?;
+                if fmt.len() < 16 {
+                    return Err("WAV format chunk is too short".to_string());
+                }
+                let mut tag = u16::from_le_bytes([fmt[0], fmt[1]]);
+                // WAVE_FORMAT_EXTENSIBLE keeps the real format at the start of the sub-format GUID.
+                if tag == 0xFFFE && fmt.len() >= 26 {
+                    tag = u16::from_le_bytes([fmt[24], fmt[25]]);
+                }
+                let parsed = WavFormat {
+                    channels: u16::from_le_bytes([fmt[2], fmt[3]]),
+                    sample_rate: u32::from_le_bytes([fmt[4], fmt[5], fmt[6], fmt[7]]),
+                    bits_per_sample: u16::from_le_bytes([fmt[14], fmt[15]]),
+                    float: tag == 3,
+                };
+                let supported = matches!((tag, parsed.bits_per_sample), (1, 8 | 16 | 24 | 32) | (3, 32));
+                if !supported || parsed.channels == 0 || parsed.sample_rate == 0 {
+                    return Err(format!("Unsupported WAV format {} with {} bit samples", tag, parsed.bits_per_sample));
+                }
+                format = Some(parsed);
+            }
+            _ => {
+                io::copy(&mut Read::take(&mut *input, padded as u64), &mut io::sink()).map_err(|e| e.to_string())?;
+            }
+        }
+    }
+    format.ok_or_else(|| "WAV file has no format chunk".to_string())
+}
+
+fn sample_value(bytes: &[u8], format: &WavFormat) -> f32 {
+    match (format.bits_per_sample, format.float) {
+        // 8 bit WAV samples are unsigned.
+        (8, _) => (bytes[0] as f32 - 128.0) / 128.0,
+        (16, _) => i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 32768.0,
+        (24, _) => (i32::from_le_bytes([0, bytes[0], bytes[1], bytes[2]]) >> 8) as f32 / 8388608.0,
+        (_, true) => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        _ => i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f32 / 2147483648.0,
+    }
+}
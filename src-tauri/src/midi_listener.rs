@@ -0,0 +1,101 @@
+use std::sync::mpsc;
+use midir::{MidiInput, MidiInputConnection};
+
+use crate::midi_parser::{self, MidiParser};
+use crate::protocols::{MidiCommand, MidiData, MidiPortInfo};
+
+const CLIENT_NAME: &str = "fluxviewer";
+
+pub struct Receiver {
+    source: String,
+    parser: MidiParser,
+    tx: mpsc::Sender<MidiData>,
+}
+
+// midir delivers messages on its own thread, so this thread only manages the
+// connection, which stays open for as long as it is held.
+pub fn start(tx: mpsc::Sender<MidiData>, command_rx: mpsc::Receiver<MidiCommand>) {
+    let mut connection: Option<MidiInputConnection<Receiver>> = None;
+
+    while let Ok(command) = command_rx.recv() {
+        if let Some(c) = connection.take() {
+            c.close();
+        }
+        match command {
+            MidiCommand::Start { port } => {
+                connection = match connect(&port, tx.clone()) {
+                    Ok(c) => {
+                        eprintln!("MIDI Listener started on {}", port);
+                        Some(c)
+                    }
+                    Err(e) => {
+                        eprintln!("Failed to open MIDI port {}: {}", port, e);
+                        None
+                    }
+                };
+            }
+            MidiCommand::StartVirtual { name } => {
+                connection = match create_virtual(&name, tx.clone()) {
+                    Ok(c) => {
+                        eprintln!("MIDI Listener started on virtual port {}", name);
+                        Some(c)
+                    }
+                    Err(e) => {
+                        eprintln!("Failed to create virtual MIDI port {}: {}", name, e);
+                        None
+                    }
+                };
+            }
+            MidiCommand::Stop => {
+                eprintln!("MIDI Listener stopped");
+            }
+        }
+    }
+}
+
+pub fn list_ports() -> Vec<MidiPortInfo> {
+    let Ok(input) = MidiInput::new(CLIENT_NAME) else {
+        return Vec::new();
+    };
+    input.ports().iter()
+        .filter_map(|p| input.port_name(p).ok())
+        .map(|name| MidiPortInfo { name })
+        .collect()
+}
+
+fn on_message(_timestamp: u64, bytes: &[u8], receiver: &mut Receiver) {
+    for (message, raw) in receiver.parser.parse(bytes) {
+        receiver.tx.send(midi_parser::midi_data(&receiver.source, message, raw)).ok();
+    }
+}
+
+pub fn connect(port_name: &str, tx: mpsc::Sender<MidiData>) -> Result<MidiInputConnection<Receiver>, String> {
+    let input = MidiInput::new(CLIENT_NAME).map_err(|e| e.to_string())?;
+    let port = input.ports().into_iter()
+        .find(|p| input.port_name(p).is_ok_and(|name| name == port_name))
+        .ok_or_else(|| "No such port".to_string())?;
+    let receiver = Receiver {
+        source: port_name.to_string(),
+        parser: MidiParser::default(),
+        tx,
+    };
+    input.connect(&port, "fluxviewer-in", on_message, receiver).map_err(|e| e.to_string())
+}
+
+#[cfg(unix)]
+pub fn create_virtual(name: &str, tx: mpsc::Sender<MidiData>) -> Result<MidiInputConnection<Receiver>, String> {
+    use midir::os::unix::VirtualInput;
+
+    let input = MidiInput::new(CLIENT_NAME).map_err(|e| e.to_string())?;
+    let receiver = Receiver {
+        source: name.to_string(),
+        parser: MidiParser::default(),
+        tx,
+    };
+    input.create_virtual(name, on_message, receiver).map_err(|e| e.to_string())
+}
+
+#[cfg(not(unix))]
+pub fn create_virtual(_name: &str, _tx: mpsc::Sender<MidiData>) -> Result<MidiInputConnection<Receiver>, String> {
+    Err("Virtual MIDI ports are not supported on this platform".to_string())
+}
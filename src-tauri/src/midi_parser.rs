@@ -0,0 +1,141 @@
+use chrono::Local;
+
+use crate::mtc::{self, MtcDecoder};
+use crate::msc;
+use crate::protocols::{MidiData, MidiMessage};
+
+const SYSEX_START: u8 = 0xF0;
+const SYSEX_END: u8 = 0xF7;
+const MTC_QUARTER_FRAME: u8 = 0xF1;
+// Longer SysEx messages are dropped rather than buffered without bound.
+const MAX_SYSEX_LENGTH: usize = 65536;
+
+// Decodes a raw MIDI byte stream as it arrives from a DIN port, handling running
+// status and real-time bytes interleaved into other messages. Show Control and
+// timecode are decoded too, with the running timecode kept per stream.
+#[derive(Default)]
+pub struct MidiParser {
+    status: Option<u8>,
+    data: Vec<u8>,
+    sysex: Option<Vec<u8>>,
+    mtc: MtcDecoder,
+}
+
+impl MidiParser {
+    // Returns each completed message with the bytes it was decoded from.
+    pub fn parse(&mut self, bytes: &[u8]) -> Vec<(MidiMessage, Vec<u8>)> {
+        bytes.iter().filter_map(|&b| self.push(b)).collect()
+    }
+
+    pub fn push(&mut self, byte: u8) -> Option<(MidiMessage, Vec<u8>)> {
+        match byte {
+            // Real-time messages may appear anywhere, even inside SysEx.
+            0xF8..=0xFF => Some((MidiMessage::System { status: byte, data: Vec::new() }, vec![byte])),
+            SYSEX_START => {
+                self.status = None;
+                self.data.clear();
+                self.sysex = Some(Vec::new());
+                None
+            }
+            SYSEX_END => {
+                let data = self.sysex.take()?;
+                let mut raw = Vec::with_capacity(data.len() + 2);
+                raw.push(SYSEX_START);
+                raw.extend_from_slice(&data);
+                raw.push(SYSEX_END);
+                Some((self.decode_sysex(data), raw))
+            }
+            0x80..=0xF6 => {
+                // Any other status byte ends an unterminated SysEx.
+                self.sysex = None;
+                self.data.clear();
+                self.status = Some(byte);
+                if data_length(byte) == Some(0) {
+                    self.status = None;
+                    return decode(byte, &[]).map(|message| (message, vec![byte]));
+                }
+                None
+            }
+            _ => {
+                if let Some(ref mut sysex) = self.sysex {
+                    if sysex.len() < MAX_SYSEX_LENGTH {
+                        sysex.push(byte);
+                    } else {
+                        self.sysex = None;
+                    }
+                    return None;
+                }
+
+                let status = self.status?;
+                let length = data_length(status)?;
+                self.data.push(byte);
+                if self.data.len() < length {
+                    return None;
+                }
+
+                let data = std::mem::take(&mut self.data);
+                // Running status only applies to channel messages.
+                if status >= 0xF0 {
+                    self.status = None;
+                }
+                let mut raw = Vec::with_capacity(data.len() + 1);
+                raw.push(status);
+                raw.extend_from_slice(&data);
+                if status == MTC_QUARTER_FRAME {
+                    let timecode = self.mtc.quarter_frame(data[0]);
+                    let message = MidiMessage::TimecodeQuarterFrame { piece: data[0] >> 4, value: data[0] & 0x0F, timecode };
+                    return Some((message, raw));
+                }
+                decode(status, &data).map(|message| (message, raw))
+            }
+        }
+    }
+
+    fn decode_sysex(&mut self, data: Vec<u8>) -> MidiMessage {
+        if let Some((device_id, timecode)) = mtc::decode_full_frame(&data) {
+            self.mtc.full_frame(timecode);
+            return MidiMessage::TimecodeFullFrame { device_id, timecode };
+        }
+        msc::decode(&data).unwrap_or(MidiMessage::SysEx { data })
+    }
+}
+
+pub fn midi_data(source: &str, message: MidiMessage, bytes: Vec<u8>) -> MidiData {
+    MidiData {
+        timestamp: Local::now().format("%H:%M:%S%.3f").to_string(),
+        source: source.to_string(),
+        message,
+        bytes,
+    }
+}
+
+fn data_length(status: u8) -> Option<usize> {
+    match status {
+        0x80..=0xBF | 0xE0..=0xEF => Some(2),
+        0xC0..=0xDF => Some(1),
+        0xF1 | 0xF3 => Some(1),
+        0xF2 => Some(2),
+        0xF6 => Some(0),
+        // 0xF4 and 0xF5 are undefined.
+        _ => None,
+    }
+}
+
+fn decode(status: u8, data: &[u8]) -> Option<MidiMessage> {
+    let channel = (status & 0x0F) + 1;
+    let message = match status & 0xF0 {
+        0x80 => MidiMessage::NoteOff { channel, note: data[0], velocity: data[1] },
+        0x90 => MidiMessage::NoteOn { channel, note: data[0], velocity: data[1] },
+        0xA0 => MidiMessage::PolyPressure { channel, note: data[0], pressure: data[1] },
+        0xB0 => MidiMessage::ControlChange { channel, controller: data[0], value: data[1] },
+        0xC0 => MidiMessage::ProgramChange { channel, program: data[0] },
+        0xD0 => MidiMessage::ChannelPressure { channel, pressure: data[0] },
+        0xE0 => MidiMessage::PitchBend {
+            channel,
+            value: ((data[1] as i16) << 7 | data[0] as i16) - 8192,
+        },
+        0xF0 => MidiMessage::System { status, data: data.to_vec() },
+        _ => return None,
+    };
+    Some(message)
+}
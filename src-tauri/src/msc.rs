@@ -0,0 +1,204 @@
+use crate::protocols::{MidiMessage, MscCommand, MscCommandFormat};
+
+// MIDI Show Control: F0 7F <device> 02 <command format> <command> <data> F7.
+pub fn decode(sysex: &[u8]) -> Option<MidiMessage> {
+    let [0x7F, device_id, 0x02, format, command, ref data @ ..] = *sysex else {
+        return None;
+    };
+    let command = command_from_code(command);
+
+    let mut cue = None;
+    let mut list = None;
+    let mut path = None;
+    match command {
+        MscCommand::Go | MscCommand::Stop | MscCommand::Resume | MscCommand::Load | MscCommand::GoOff | MscCommand::GoJamClock => {
+            (cue, list, path) = cue_fields(data);
+        }
+        // Timed go carries the start time (hr mn sc fr ff) ahead of the cue.
+        MscCommand::TimedGo if data.len() >= 5 => {
+            (cue, list, path) = cue_fields(&data[5..]);
+        }
+        MscCommand::StandbyPlus
+        | MscCommand::StandbyMinus
+        | MscCommand::SequencePlus
+        | MscCommand::SequenceMinus
+        | MscCommand::StartClock
+        | MscCommand::StopClock
+        | MscCommand::ZeroClock
+        | MscCommand::MtcChaseOn
+        | MscCommand::MtcChaseOff
+        | MscCommand::OpenCueList
+        | MscCommand::CloseCueList => {
+            list = field(data.split(|&b| b == 0x00).next().unwrap_or_default());
+        }
+        MscCommand::OpenCuePath | MscCommand::CloseCuePath => {
+            path = field(data.split(|&b| b == 0x00).next().unwrap_or_default());
+        }
+        _ => {}
+    }
+
+    Some(MidiMessage::ShowControl {
+        device_id,
+        command_format: format_from_code(format),
+        command,
+        cue,
+        list,
+        path,
+    })
+}
+
+// Cue number, list and path are separated by 0x00; trailing fields may be left out.
+fn cue_fields(data: &[u8]) -> (Option<String>, Option<String>, Option<String>) {
+    let mut fields = data.split(|&b| b == 0x00).map(field);
+    (
+        fields.next().flatten(),
+        fields.next().flatten(),
+        fields.next().flatten(),
+    )
+}
+
+// Fields are ASCII digits and '.'; anything else is dropped.
+fn field(bytes: &[u8]) -> Option<String> {
+    let text: String = bytes.iter()
+        .filter(|b| b.is_ascii_digit() || **b == b'.')
+        .map(|&b| b as char)
+        .collect();
+    (!text.is_empty()).then_some(text)
+}
+
+fn command_from_code(code: u8) -> MscCommand {
+    match code {
+        0x01 => MscCommand::Go,
+        0x02 => MscCommand::Stop,
+        0x03 => MscCommand::Resume,
+        0x04 => MscCommand::TimedGo,
+        0x05 => MscCommand::Load,
+        0x06 => MscCommand::Set,
+        0x07 => MscCommand::Fire,
+        0x08 => MscCommand::AllOff,
+        0x09 => MscCommand::Restore,
+        0x0A => MscCommand::Reset,
+        0x0B => MscCommand::GoOff,
+        0x10 => MscCommand::GoJamClock,
+        0x11 => MscCommand::StandbyPlus,
+        0x12 => MscCommand::StandbyMinus,
+        0x13 => MscCommand::SequencePlus,
+        0x14 => MscCommand::SequenceMinus,
+        0x15 => MscCommand::StartClock,
+        0x16 => MscCommand::StopClock,
+        0x17 => MscCommand::ZeroClock,
+        0x18 => MscCommand::SetClock,
+        0x19 => MscCommand::MtcChaseOn,
+        0x1A => MscCommand::MtcChaseOff,
+        0x1B => MscCommand::OpenCueList,
+        0x1C => MscCommand::CloseCueList,
+        0x1D => MscCommand::OpenCuePath,
+        0x1E => MscCommand::CloseCuePath,
+        other => MscCommand::Other(other),
+    }
+}
+
+fn format_from_code(code: u8) -> MscCommandFormat {
+    match code {
+        0x01 => MscCommandFormat::Lighting,
+        0x02 => MscCommandFormat::MovingLights,
+        0x03 => MscCommandFormat::ColorChangers,
+        0x04 => MscCommandFormat::Strobes,
+        0x05 => MscCommandFormat::Lasers,
+        0x06 => MscCommandFormat::Chasers,
+        0x10 => MscCommandFormat::Sound,
+        0x11 => MscCommandFormat::Music,
+        0x12 => MscCommandFormat::CdPlayers,
+        0x13 => MscCommandFormat::EpromPlayback,
+        0x14 => MscCommandFormat::AudioTapeMachines,
+        0x15 => MscCommandFormat::Intercoms,
+        0x16 => MscCommandFormat::Amplifiers,
+        0x17 => MscCommandFormat::AudioEffects,
+        0x18 => MscCommandFormat::Equalizers,
+        0x20 => MscCommandFormat::Machinery,
+        0x21 => MscCommandFormat::Rigging,
+        0x22 => MscCommandFormat::Flys,
+        0x23 => MscCommandFormat::Lifts,
+        0x24 => MscCommandFormat::Turntables,
+        0x25 => MscCommandFormat::Trusses,
+        0x26 => MscCommandFormat::Robots,
+        0x27 => MscCommandFormat::Animation,
+        0x28 => MscCommandFormat::Floats,
+        0x29 => MscCommandFormat::Breakaways,
+        0x2A => MscCommandFormat::Barges,
+        0x30 => MscCommandFormat::Video,
+        0x31 => MscCommandFormat::VideoTapeMachines,
+        0x32 => MscCommandFormat::VideoCassetteMachines,
+        0x33 => MscCommandFormat::VideoDiscPlayers,
+        0x34 => MscCommandFormat::VideoSwitchers,
+        0x35 => MscCommandFormat::VideoEffects,
+        0x36 => MscCommandFormat::VideoCharacterGenerators,
+        0x37 => MscCommandFormat::VideoStillStores,
+        0x38 => MscCommandFormat::VideoMonitors,
+        0x40 => MscCommandFormat::Projection,
+        0x41 => MscCommandFormat::FilmProjectors,
+        0x42 => MscCommandFormat::SlideProjectors,
+        0x43 => MscCommandFormat::VideoProjectors,
+        0x44 => MscCommandFormat::Dissolvers,
+        0x45 => MscCommandFormat::ShutterControls,
+        0x50 => MscCommandFormat::ProcessControl,
+        0x51 => MscCommandFormat::HydraulicOil,
+        0x52 => MscCommandFormat::H2o,
+        0x53 => MscCommandFormat::Co2,
+        0x54 => MscCommandFormat::CompressedAir,
+        0x55 => MscCommandFormat::NaturalGas,
+        0x56 => MscCommandFormat::Fog,
+        0x57 => MscCommandFormat::Smoke,
+        0x58 => MscCommandFormat::CrackedHaze,
+        0x60 => MscCommandFormat::Pyro,
+        0x61 => MscCommandFormat::Fireworks,
+        0x62 => MscCommandFormat::Explosions,
+        0x63 => MscCommandFormat::Flame,
+        0x64 => MscCommandFormat::SmokePots,
+        0x7F => MscCommandFormat::AllTypes,
+        other => MscCommandFormat::Other(other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn show_control(format: MscCommandFormat, command: MscCommand, cue: Option<&str>, list: Option<&str>, path: Option<&str>) -> MidiMessage {
+        MidiMessage::ShowControl {
+            device_id: 0x01,
+            command_format: format,
+            command,
+            cue: cue.map(String::from),
+            list: list.map(String::from),
+            path: path.map(String::from),
+        }
+    }
+
+    #[test]
+    fn decode_show_control() {
+        let lighting = MscCommandFormat::Lighting;
+        let cases: &[(&[u8], Option<MidiMessage>)] = &[
+            (&[0x7F, 0x01, 0x02, 0x01, 0x01], Some(show_control(lighting, MscCommand::Go, None, None, None))),
+            (
+                &[0x7F, 0x01, 0x02, 0x01, 0x01, b'1', b'.', b'5', 0x00, b'2', 0x00, b'3'],
+                Some(show_control(lighting, MscCommand::Go, Some("1.5"), Some("2"), Some("3"))),
+            ),
+            (&[0x7F, 0x01, 0x02, 0x01, 0x02, 0x00, b'4'], Some(show_control(lighting, MscCommand::Stop, None, Some("4"), None))),
+            (
+                &[0x7F, 0x01, 0x02, 0x01, 0x04, 0x01, 0x02, 0x03, 0x04, 0x00, b'7'],
+                Some(show_control(lighting, MscCommand::TimedGo, Some("7"), None, None)),
+            ),
+            (&[0x7F, 0x01, 0x02, 0x01, 0x04, 0x01], Some(show_control(lighting, MscCommand::TimedGo, None, None, None))),
+            (&[0x7F, 0x01, 0x02, 0x10, 0x1B, b'3', 0x00], Some(show_control(MscCommandFormat::Sound, MscCommand::OpenCueList, None, Some("3"), None))),
+            (&[0x7F, 0x01, 0x02, 0x7F, 0x1D, b'9'], Some(show_control(MscCommandFormat::AllTypes, MscCommand::OpenCuePath, None, None, Some("9")))),
+            (&[0x7F, 0x01, 0x02, 0x99, 0x30], Some(show_control(MscCommandFormat::Other(0x99), MscCommand::Other(0x30), None, None, None))),
+            (&[0x7F, 0x01, 0x01, 0x01, 0x01], None),
+            (&[0x7E, 0x01, 0x02, 0x01, 0x01], None),
+            (&[0x7F, 0x01, 0x02, 0x01], None),
+        ];
+        for (sysex, expected) in cases {
+            assert_eq!(&decode(sysex), expected, "{:02X?}", sysex);
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use crate::protocols::Timecode;
+use crate::timecode;
+
+// Reassembles MIDI Timecode from quarter-frame messages. Eight quarter frames
+// carry one full timecode, which describes the frame that started two frames
+// before the last piece arrived.
+#[derive(Default)]
+pub struct MtcDecoder {
+    pieces: [u8; 8],
+    // Bit per piece received since the last piece 0.
+    received: u8,
+    last_piece: Option<u8>,
+    running: Option<Timecode>,
+}
+
+impl MtcDecoder {
+    // Feeds the data byte of an F1 message and returns the running timecode.
+    pub fn quarter_frame(&mut self, data: u8) -> Option<Timecode> {
+        let piece = (data >> 4) & 0x07;
+        let value = data & 0x0F;
+
+        // Pieces must arrive in order; anything else (reverse play, a jump or a
+        // dropped message) drops the running value until the next full sequence.
+        let in_order = self.last_piece.is_some_and(|last| piece == (last + 1) % 8);
+        self.last_piece = Some(piece);
+        if !in_order {
+            self.running = None;
+        }
+        if piece == 0 {
+            self.received = 0;
+        } else if !in_order {
+            self.received = 0;
+            return None;
+        }
+        self.pieces[piece as usize] = value;
+        self.received |= 1 << piece;
+
+        match piece {
+            7 if self.received == 0xFF => {
+                let assembled = self.assemble();
+                self.running = Some(timecode::next_frame(timecode::next_frame(assembled)));
+            }
+            // Four quarter frames make a frame.
+            3 => self.running = self.running.map(timecode::next_frame),
+            _ => {}
+        }
+        self.running
+    }
+
+    // A full-frame message sets the position outright, e.g. after a locate.
+    pub fn full_frame(&mut self, tc: Timecode) {
+        self.running = Some(tc);
+        self.received = 0;
+        // Quarter frames resume from piece 0 after a locate.
+        self.last_piece = Some(7);
+    }
+
+    fn assemble(&self) -> Timecode {
+        let p = &self.pieces;
+        Timecode {
+            frames: p[0] | (p[1] & 0x01) << 4,
+            seconds: p[2] | (p[3] & 0x03) << 4,
+            minutes: p[4] | (p[5] & 0x03) << 4,
+            hours: p[6] | (p[7] & 0x01) << 4,
+            rate: timecode::rate_from_code(p[7] >> 1),
+        }
+    }
+}
+
+// Full frame: F0 7F <device> 01 01 hr mn sc fr F7, where hr is 0rrhhhhh.
+pub fn decode_full_frame(sysex: &[u8]) -> Option<(u8, Timecode)> {
+    match *sysex {
+        [0x7F, device_id, 0x01, 0x01, hr, mn, sc, fr] => Some((
+            device_id,
+            Timecode {
+                hours: hr & 0x1F,
+                minutes: mn & 0x3F,
+                seconds: sc & 0x3F,
+                frames: fr & 0x1F,
+                rate: timecode::rate_from_code(hr >> 5),
+            },
+        )),
+        _ => None,
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocols::TimecodeRate;
+
+    type FullFrameCase = (&'static [u8], Option<(u8, Timecode)>);
+
+    fn tc(hours: u8, minutes: u8, seconds: u8, frames: u8) -> Timecode {
+        Timecode { hours, minutes, seconds, frames, rate: TimecodeRate::Ebu }
+    }
+
+    // Quarter frames for 01:02:03:04 at 25 fps.
+    const SEQUENCE: [u8; 8] = [0x04, 0x10, 0x23, 0x30, 0x42, 0x50, 0x61, 0x72];
+
+    #[test]
+    fn quarter_frames() {
+        let mut decoder = MtcDecoder::default();
+        let running: Vec<_> = SEQUENCE.iter().chain(&SEQUENCE[..4]).map(|&data| decoder.quarter_frame(data)).collect();
+        let mut expected = vec![None; 7];
+        expected.extend([Some(tc(1, 2, 3, 6)), Some(tc(1, 2, 3, 6)), Some(tc(1, 2, 3, 6)), Some(tc(1, 2, 3, 6)), Some(tc(1, 2, 3, 7))]);
+        assert_eq!(running, expected);
+
+        // A piece out of order drops the running value until the next full sequence.
+        assert_eq!(decoder.quarter_frame(SEQUENCE[6]), None);
+        assert_eq!(decoder.quarter_frame(SEQUENCE[7]), None);
+        let running: Vec<_> = SEQUENCE.iter().map(|&data| decoder.quarter_frame(data)).collect();
+        assert_eq!(running.last(), Some(&Some(tc(1, 2, 3, 6))));
+    }
+
+    #[test]
+    fn quarter_frames_after_full_frame() {
+        let mut decoder = MtcDecoder::default();
+        decoder.full_frame(tc(10, 0, 0, 0));
+        let running: Vec<_> = SEQUENCE[..4].iter().map(|&data| decoder.quarter_frame(data)).collect();
+        assert_eq!(running, [Some(tc(10, 0, 0, 0)), Some(tc(10, 0, 0, 0)), Some(tc(10, 0, 0, 0)), Some(tc(10, 0, 0, 1))]);
+    }
+
+    #[test]
+    fn full_frame() {
+        let cases: &[FullFrameCase] = &[
+            (&[0x7F, 0x7F, 0x01, 0x01, 0x21, 0x02, 0x03, 0x04], Some((0x7F, tc(1, 2, 3, 4)))),
+            (&[0x7F, 0x05, 0x01, 0x01, 0x77, 0x3B, 0x3B, 0x1D], Some((0x05, Timecode { rate: TimecodeRate::Smpte, ..tc(23, 59, 59, 29) }))),
+            (&[0x7F, 0x7F, 0x01, 0x02, 0x21, 0x02, 0x03, 0x04], None),
+            (&[0x7F, 0x7F, 0x01, 0x01, 0x21, 0x02, 0x03], None),
+        ];
+        for (sysex, expected) in cases {
+            assert_eq!(&decode_full_frame(sysex), expected, "{:02X?}", sysex);
+        }
+    }
+}
@@ -0,0 +1,255 @@
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Mutex;
+
+use crate::protocols::{OscData, OscFilterAction, OscFilterRule};
+
+struct Cidr {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    // Accepts "10.0.0.0/8", "fe80::/10" or a bare address for a single host.
+    fn parse(spec: &str) -> Result<Cidr, String> {
+        let invalid = || format!("Invalid sender range {}", spec);
+        let (ip, prefix) = match spec.split_once('/') {
+            Some((ip, prefix)) => (ip, Some(prefix)),
+            None => (spec, None),
+        };
+        let network: IpAddr = ip.trim().parse().map_err(|_| invalid())?;
+        let max = if network.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(p) => p.trim().parse().map_err(|_| invalid())?,
+            None => max,
+        };
+        if prefix > max {
+            return Err(invalid());
+        }
+        Ok(Cidr { network, prefix })
+    }
+
+    fn contains(&self, ip: IpAddr) -> bool {
+        match (self.network, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+struct CompiledRule {
+    rule: OscFilterRule,
+    sender: Option<Cidr>,
+}
+
+impl CompiledRule {
+    fn matches(&self, osc: &OscData) -> bool {
+        if let Some(ref pattern) = self.rule.address {
+            if !pattern_matches(pattern, &osc.message.address) {
+                return false;
+            }
+        }
+        if let Some(ref cidr) = self.sender {
+            match osc.sender.parse::<SocketAddr>() {
+                Ok(addr) if cidr.contains(addr.ip()) => {}
+                _ => return false,
+            }
+        }
+        true
+    }
+}
+
+// Rules are checked in order and the first match decides. Messages no rule
+// matches are dropped once any allow rule exists, and passed otherwise.
+#[derive(Default)]
+pub struct OscFilter {
+    rules: Mutex<Vec<CompiledRule>>,
+}
+
+impl OscFilter {
+    pub fn set_rules(&self, rules: Vec<OscFilterRule>) -> Result<(), String> {
+        let mut compiled = Vec::with_capacity(rules.len());
+        for rule in rules {
+            if let Some(ref pattern) = rule.address {
+                validate_pattern(pattern)?;
+            }
+            let sender = rule.sender.as_deref().map(Cidr::parse).transpose()?;
+            compiled.push(CompiledRule { rule, sender });
+        }
+        *self.rules.lock().unwrap() = compiled;
+        Ok(())
+    }
+
+    pub fn rules(&self) -> Vec<OscFilterRule> {
+        self.rules.lock().unwrap().iter().map(|r| r.rule.clone()).collect()
+    }
+
+    pub fn allows(&self, osc: &OscData) -> bool {
+        let rules = self.rules.lock().unwrap();
+        match rules.iter().find(|r| r.matches(osc)) {
+            Some(r) => r.rule.action == OscFilterAction::Allow,
+            None => !rules.iter().any(|r| r.rule.action == OscFilterAction::Allow),
+        }
+    }
+}
+
+// OSC 1.0 address pattern matching: `?` and `*` never match across a `/`,
+// `[a-z]` and `[!a-z]` match one character, `{foo,bar}` one of the strings.
+// OSC 1.1's `//` matches any number of address parts.
+pub fn pattern_matches(pattern: &str, address: &str) -> bool {
+    match_from(pattern.as_bytes(), address.as_bytes())
+}
+
+fn match_from(pattern: &[u8], address: &[u8]) -> bool {
+    match pattern {
+        [] => address.is_empty(),
+        [b'/', b'/', ..] => {
+            let rest = &pattern[1..];
+            (0..address.len()).any(|i| address[i] == b'/' && match_from(rest, &address[i..]))
+        }
+        [b'*', rest @ ..] => {
+            for i in 0..=address.len() {
+                if match_from(rest, &address[i..]) {
+                    return true;
+                }
+                if address.get(i) == Some(&b'/') {
+                    break;
+                }
+            }
+            false
+        }
+        [b'?', rest @ ..] => match address.split_first() {
+            Some((&c, tail)) => c != b'/' && match_from(rest, tail),
+            None => false,
+        },
+        [b'[', ..] => {
+            let Some(close) = pattern.iter().position(|&c| c == b']') else {
+                return false;
+            };
+            let Some((&c, tail)) = address.split_first() else {
+                return false;
+            };
+            c != b'/' && class_matches(&pattern[1..close], c) && match_from(&pattern[close + 1..], tail)
+        }
+        [b'{', ..] => {
+            let Some(close) = pattern.iter().position(|&c| c == b'}') else {
+                return false;
+            };
+            let rest = &pattern[close + 1..];
+            pattern[1..close]
+                .split(|&c| c == b',')
+                .any(|alt| address.starts_with(alt) && match_from(rest, &address[alt.len()..]))
+        }
+        [c, rest @ ..] => address.first() == Some(c) && match_from(rest, &address[1..]),
+    }
+}
+
+fn class_matches(class: &[u8], c: u8) -> bool {
+    let (negate, class) = match class.split_first() {
+        Some((b'!', rest)) => (true, rest),
+        _ => (false, class),
+    };
+    let mut found = false;
+    let mut i = 0;
+    while i < class.len() {
+        // A `-` first or last is a literal dash.
+        if i + 2 < class.len() && class[i + 1] == b'-' {
+            found |= (class[i]..=class[i + 2]).contains(&c);
+            i += 3;
+        } else {
+            found |= class[i] == c;
+            i += 1;
+        }
+    }
+    found != negate
+}
+
+fn validate_pattern(pattern: &str) -> Result<(), String> {
+    if !pattern.starts_with('/') {
+        return Err(format!("Address pattern must start with '/': {}", pattern));
+    }
+    let mut open: Option<char> = None;
+    for c in pattern.chars() {
+        match (open, c) {
+            (None, '[') => open = Some(']'),
+            (None, '{') => open = Some('}'),
+            (Some(close), c) if c == close => open = None,
+            (Some(_), '[' | '{') => return Err(format!("Nested brackets in address pattern {}", pattern)),
+            _ => {}
+        }
+    }
+    match open {
+        Some(close) => Err(format!("Missing '{}' in address pattern {}", close, pattern)),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patterns() {
+        let cases = [
+            ("/eos/out/*", "/eos/out/cue", true),
+            ("/eos/out/*", "/eos/out/cue/1", false),
+            ("/eos/*/cue", "/eos/out/cue", true),
+            ("/fader?", "/fader1", true),
+            ("/fader?", "/fader", false),
+            ("/a?b", "/a/b", false),
+            ("//cue", "/eos/out/cue", true),
+            ("//cue", "/cue", true),
+            ("/eos//fire", "/eos/out/event/fire", true),
+            ("//cue", "/eos/out/cuelist", false),
+            ("/ch[1-3]", "/ch2", true),
+            ("/ch[1-3]", "/ch4", false),
+            ("/ch[!a-z]", "/ch4", true),
+            ("/ch[!a-z]", "/chx", false),
+            ("/ch[-a]", "/ch-", true),
+            ("/ch[a-]", "/ch-", true),
+            ("/ch[a-]", "/chb", false),
+            ("/ch[!-]", "/ch-", false),
+            ("/ch[a]", "/ch/", false),
+            ("/{go,stop}", "/go", true),
+            ("/{go,stop}", "/stop", true),
+            ("/{go,stop}", "/pause", false),
+            ("/{go,go_off}/1", "/go_off/1", true),
+            ("/exact", "/exact", true),
+            ("/exact", "/exactly", false),
+        ];
+        for (pattern, address, expected) in cases {
+            assert_eq!(pattern_matches(pattern, address), expected, "{} {}", pattern, address);
+        }
+    }
+
+    #[test]
+    fn cidr() {
+        let cases = [
+            ("10.0.0.0/8", "10.1.2.3", true),
+            ("10.0.0.0/8", "11.0.0.1", false),
+            ("0.0.0.0/0", "192.0.2.1", true),
+            ("192.0.2.1/32", "192.0.2.1", true),
+            ("192.0.2.1/32", "192.0.2.2", false),
+            ("192.0.2.1", "192.0.2.2", false),
+            ("fe80::/10", "fe80::1", true),
+            ("fe80::/10", "fec0::1", false),
+            ("::/0", "2001:db8::1", true),
+            ("2001:db8::1/128", "2001:db8::1", true),
+            ("2001:db8::1/128", "2001:db8::2", false),
+            ("0.0.0.0/0", "::1", false),
+        ];
+        for (spec, ip, expected) in cases {
+            let cidr = Cidr::parse(spec).unwrap();
+            assert_eq!(cidr.contains(ip.parse().unwrap()), expected, "{} {}", spec, ip);
+        }
+        for spec in ["10.0.0.0/33", "::/129", "10.0.0/8", "10.0.0.0/x"] {
+            assert!(Cidr::parse(spec).is_err(), "{}", spec);
+        }
+    }
+}
@@ -1,105 +1,332 @@
+use std::io::Read;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket};
+use chrono::{DateTime, Local};
+use std::time::{Duration, Instant};
+use crate::protocols::{OscConnection, OscData, OscCommand, OscTcpFraming, OscTransport, SerialFraming};
+use crate::osc_proxy::OscProxy;
+use crate::serial_framing::FrameDecoder;
 
-use std::sync::mpsc;
-use rosc;
-use std::net::UdpSocket;
-use chrono::Local;
-use std::time::Duration;
-use crate::protocols::{OscData, OscCommand};
-use crate::osc_message_data::{OscMessageData, OscArgData};
-
-pub fn start(tx: mpsc::Sender<OscData>, command_rx: mpsc::Receiver<OscCommand>) {
-    let mut socket: Option<UdpSocket> = None;
-    let mut buf: [u8; 1024] = [0u8; 1024];
+pub type ConnectionTable = Arc<Mutex<Vec<OscConnection>>>;
+
+// TCP sockets are polled without blocking, so the thread naps between polls.
+const TCP_POLL_INTERVAL: Duration = Duration::from_millis(10);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(2);
+const LENGTH_PREFIX_WIDTH: usize = 4;
+// Seconds between the NTP epoch (1900) and the Unix epoch.
+const NTP_UNIX_OFFSET: u32 = 2_208_988_800;
+
+static NEXT_BUNDLE_ID: AtomicU64 = AtomicU64::new(1);
+
+// A connect running on its own thread, checked on each poll so the listener never blocks on it.
+struct PendingConnect {
+    result_rx: mpsc::Receiver<Result<(TcpStream, SocketAddr), String>>,
+    first_attempt: bool,
+}
+
+struct TcpConnection {
+    stream: TcpStream,
+    info: OscConnection,
+    decoder: FrameDecoder,
+}
+
+enum Listener {
+    Udp(UdpSocket),
+    TcpServer {
+        listener: TcpListener,
+        framing: OscTcpFraming,
+        connections: Vec<TcpConnection>,
+    },
+    TcpClient {
+        addr: String,
+        framing: OscTcpFraming,
+        connection: Option<Box<TcpConnection>>,
+        pending: Option<PendingConnect>,
+        last_attempt: Option<Instant>,
+    },
+}
+
+pub fn start(
+    tx: mpsc::Sender<OscData>,
+    command_rx: mpsc::Receiver<OscCommand>,
+    connections_table: ConnectionTable,
+    connections_tx: mpsc::Sender<Vec<OscConnection>>,
+    proxy: Arc<OscProxy>,
+) {
+    let mut listener: Option<Listener> = None;
+    // Large enough for any UDP datagram, so big bundles aren't cut short.
+    let mut buf = vec![0u8; 65536];
     loop{
         match command_rx.try_recv() {
-            Ok(OscCommand::Start { ip, port}) => {
+            Ok(OscCommand::Start { ip, port, transport }) => {
                 let addr = format!("{}:{}", ip, port);
-                match UdpSocket::bind(&addr) {
-                    Ok(s) => {
-                        s.set_read_timeout(Some(Duration::from_millis(100))).ok();
-                        socket = Some(s);
-                        println!("OSC Listener started on {}", addr);
+                listener = match open(&addr, transport) {
+                    Ok(l) => {
+                        eprintln!("OSC Listener started on {} ({:?})", addr, transport);
+                        Some(l)
                     },
                     Err(e) => {
-                        eprintln!("Failed to bind OSC Listener to {}: {}", addr, e);
-                        socket = None;
+                        eprintln!("Failed to start OSC Listener on {}: {}", addr, e);
+                        None
                     }
-                }
+                };
+                publish_connections(&listener, &connections_table, &connections_tx);
             },
             Ok(OscCommand::Stop) => {
-                socket = None;
-                println!("OSC Listener stopped");
+                listener = None;
+                publish_connections(&listener, &connections_table, &connections_tx);
+                eprintln!("OSC Listener stopped");
             },
+            // With nothing open and no commands left to come, the listener is done.
+            Err(mpsc::TryRecvError::Disconnected) if listener.is_none() => return,
             Err(_) => {}
         }
 
-        if let Some(ref s) = socket {
-            match s.recv_from(&mut buf) {
-                Ok((num_bytes, source_addr)) => {
-                    match rosc::decoder::decode_udp(&buf[..num_bytes]) {
-                        Ok((_, packet)) => {
-                            let timestamp = Local::now().format("%H:%M:%S%.3f").to_string();
-                            process_packet(packet, &tx, &timestamp, source_addr.to_string());
-                        }
-                        Err(e) => {
-                            eprintln!("Error decoding OSC packet: {}", e);
+        let connections_changed = match listener {
+            Some(Listener::Udp(ref s)) => {
+                match s.recv_from(&mut buf) {
+                    Ok((num_bytes, source_addr)) => {
+                        proxy.forward(&buf[..num_bytes]);
+                        decode_packet(&buf[..num_bytes], &tx, source_addr.to_string());
+                    }
+                    Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                        // Timeout reached, continue to next iteration
+                    }
+                    Err(ref e) if e.kind() == std::io::ErrorKind::TimedOut=> {
+                        // Interrupted, continue to next iteration
+                    }
+                    Err(e) => {
+                        eprintln!("Error receiving from UDP socket: {}", e);
+                    }
+                }
+                false
+            }
+            Some(Listener::TcpServer { ref listener, framing, ref mut connections }) => {
+                let before = connections.len();
+                let mut accepted = false;
+                while let Ok((stream, peer)) = listener.accept() {
+                    match accept_connection(stream, peer, framing) {
+                        Ok(c) => {
+                            eprintln!("OSC TCP connection from {}", peer);
+                            connections.push(c);
+                            accepted = true;
                         }
+                        Err(e) => eprintln!("Failed to accept OSC TCP connection from {}: {}", peer, e),
+                    }
+                }
+                connections.retain_mut(|c| {
+                    let open = read_connection(c, &mut buf, framing, &tx, &proxy);
+                    if !open {
+                        eprintln!("OSC TCP connection from {} closed", c.info.peer);
                     }
+                    open
+                });
+                std::thread::sleep(TCP_POLL_INTERVAL);
+                accepted || connections.len() != before
+            }
+            Some(Listener::TcpClient { ref addr, framing, ref mut connection, ref mut pending, ref mut last_attempt }) => {
+                let mut changed = false;
+                if connection.is_none() && pending.is_none() && last_attempt.is_none_or(|t| t.elapsed() >= RECONNECT_INTERVAL) {
+                    let first_attempt = last_attempt.is_none();
+                    *last_attempt = Some(Instant::now());
+                    *pending = Some(start_connect(addr, first_attempt));
                 }
-                Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
-                    // Timeout reached, continue to next iteration
+                if let Some(p) = pending.take() {
+                    match p.result_rx.try_recv() {
+                        Ok(result) => match result.and_then(|(stream, peer)| accept_connection(stream, peer, framing).map_err(|e| e.to_string())) {
+                            Ok(c) => {
+                                eprintln!("OSC TCP connected to {}", addr);
+                                *connection = Some(Box::new(c));
+                                changed = true;
+                            }
+                            // Keep retrying quietly so a server that isn't up yet doesn't flood the log.
+                            Err(e) if p.first_attempt => eprintln!("Failed to connect to OSC TCP server {}: {}", addr, e),
+                            Err(_) => {}
+                        },
+                        Err(mpsc::TryRecvError::Empty) => *pending = Some(p),
+                        Err(mpsc::TryRecvError::Disconnected) => {}
+                    }
                 }
-                Err(ref e) if e.kind() == std::io::ErrorKind::TimedOut=> {
-                    // Interrupted, continue to next iteration
+                if let Some(ref mut c) = connection {
+                    if !read_connection(c, &mut buf, framing, &tx, &proxy) {
+                        eprintln!("OSC TCP connection to {} closed", addr);
+                        *connection = None;
+                        changed = true;
+                    }
                 }
-                Err(e) => {
-                    eprintln!("Error receiving from UDP socket: {}", e);
-                }   
+                std::thread::sleep(TCP_POLL_INTERVAL);
+                changed
+            }
+            None => {
+                std::thread::sleep(Duration::from_millis(100));
+                false
             }
-        } else {
-            std::thread::sleep(Duration::from_millis(100));
+        };
+        if connections_changed {
+            publish_connections(&listener, &connections_table, &connections_tx);
         }
     }
 }
 
-fn process_packet(packet: rosc::OscPacket, tx: &mpsc::Sender<OscData>, timestamp: &str, sender: String) {
-    match packet {
-        rosc::OscPacket::Message(msg) => {
-            send_message(&msg, tx, timestamp, sender);
+pub fn list_connections(table: &ConnectionTable) -> Vec<OscConnection> {
+    table.lock().unwrap().clone()
+}
+
+fn open(addr: &str, transport: OscTransport) -> Result<Listener, String> {
+    match transport {
+        OscTransport::Udp => {
+            let s = UdpSocket::bind(addr).map_err(|e| e.to_string())?;
+            s.set_read_timeout(Some(Duration::from_millis(100))).ok();
+            Ok(Listener::Udp(s))
         }
-        rosc::OscPacket::Bundle(bundle) => {
-            for content in bundle.content {
-                match content {
-                    rosc::OscPacket::Message(msg) => {
-                        send_message(&msg, tx, timestamp, sender.clone());
-                    }
-                    rosc::OscPacket::Bundle(_) => {
-                        // Nested bundles not handled
-                    }
+        OscTransport::TcpServer { framing } => {
+            let listener = TcpListener::bind(addr).map_err(|e| e.to_string())?;
+            listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+            Ok(Listener::TcpServer { listener, framing, connections: Vec::new() })
+        }
+        OscTransport::TcpClient { framing } => Ok(Listener::TcpClient {
+            addr: addr.to_string(),
+            framing,
+            connection: None,
+            pending: None,
+            last_attempt: None,
+        }),
+    }
+}
+
+fn start_connect(addr: &str, first_attempt: bool) -> PendingConnect {
+    let addr = addr.to_string();
+    let (result_tx, result_rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        result_tx.send(connect(&addr)).ok();
+    });
+    PendingConnect { result_rx, first_attempt }
+}
+
+fn connect(addr: &str) -> Result<(TcpStream, SocketAddr), String> {
+    let socket_addr = addr.to_socket_addrs()
+        .map_err(|e| e.to_string())?
+        .next()
+        .ok_or_else(|| format!("Could not resolve {}", addr))?;
+    let stream = TcpStream::connect_timeout(&socket_addr, CONNECT_TIMEOUT).map_err(|e| e.to_string())?;
+    Ok((stream, socket_addr))
+}
+
+fn accept_connection(stream: TcpStream, peer: SocketAddr, framing: OscTcpFraming) -> std::io::Result<TcpConnection> {
+    stream.set_nonblocking(true)?;
+    stream.set_nodelay(true).ok();
+    let local = stream.local_addr()?;
+    let decoder = FrameDecoder::new(match framing {
+        OscTcpFraming::LengthPrefix => SerialFraming::LengthPrefix {
+            width: LENGTH_PREFIX_WIDTH as u8,
+            little_endian: false,
+            includes_header: false,
+        },
+        OscTcpFraming::Slip => SerialFraming::Slip,
+    });
+    Ok(TcpConnection {
+        stream,
+        info: OscConnection {
+            peer: peer.to_string(),
+            local: local.to_string(),
+            connected_at: Local::now().format("%H:%M:%S%.3f").to_string(),
+        },
+        decoder,
+    })
+}
+
+// Reads whatever is waiting on the connection. Returns false once it has closed.
+// One read per poll pass, so a busy connection can't starve the others or the
+// command channel.
+fn read_connection(connection: &mut TcpConnection, buf: &mut [u8], framing: OscTcpFraming, tx: &mpsc::Sender<OscData>, proxy: &OscProxy) -> bool {
+    match connection.stream.read(buf) {
+        Ok(0) => false,
+        Ok(num_bytes) => {
+            for frame in connection.decoder.push(&buf[..num_bytes]) {
+                let packet = match framing {
+                    OscTcpFraming::LengthPrefix => &frame[LENGTH_PREFIX_WIDTH.min(frame.len())..],
+                    OscTcpFraming::Slip => &frame[..],
+                };
+                if !packet.is_empty() {
+                    proxy.forward(packet);
+                    decode_packet(packet, tx, connection.info.peer.clone());
                 }
             }
+            true
+        }
+        Err(ref e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::Interrupted) => true,
+        Err(e) => {
+            eprintln!("Error receiving from OSC TCP connection {}: {}", connection.info.peer, e);
+            false
         }
     }
 }
 
-fn send_message(msg: &rosc::OscMessage, tx: &mpsc::Sender<OscData>, timestamp: &str, sender: String) {
-    let args: Vec<OscArgData> = msg.args.iter().map(|arg| {
-        match arg {
-            rosc::OscType::Int(v) => OscArgData::Int(*v),
-            rosc::OscType::Float(v) => OscArgData::Float(*v),
-            rosc::OscType::String(v) => OscArgData::String(v.clone()),
-            rosc::OscType::Bool(v) => OscArgData::Bool(*v),
-            rosc::OscType::Nil => OscArgData::Nil,
-            other => OscArgData::String(format!("{:?}", other)),
+fn publish_connections(listener: &Option<Listener>, table: &ConnectionTable, connections_tx: &mpsc::Sender<Vec<OscConnection>>) {
+    let list: Vec<OscConnection> = match listener {
+        Some(Listener::TcpServer { ref connections, .. }) => connections.iter().map(|c| c.info.clone()).collect(),
+        Some(Listener::TcpClient { connection: Some(ref c), .. }) => vec![c.info.clone()],
+        _ => Vec::new(),
+    };
+    *table.lock().unwrap() = list.clone();
+    connections_tx.send(list).ok();
+}
+
+fn decode_packet(bytes: &[u8], tx: &mpsc::Sender<OscData>, sender: String) {
+    match rosc::decoder::decode_udp(bytes) {
+        Ok((_, packet)) => {
+            let timestamp = Local::now().format("%H:%M:%S%.3f").to_string();
+            process_packet(packet, tx, &timestamp, &sender, None);
         }
-    }).collect();
+        Err(e) => {
+            eprintln!("Error decoding OSC packet: {}", e);
+        }
+    }
+}
 
+// Bundles are walked recursively; `bundle` carries the top-level bundle ID and
+// the timetag of the bundle currently being unpacked.
+fn process_packet(packet: rosc::OscPacket, tx: &mpsc::Sender<OscData>, timestamp: &str, sender: &str, bundle: Option<(u64, Option<String>)>) {
+    match packet {
+        rosc::OscPacket::Message(msg) => {
+            send_message(&msg, tx, timestamp, sender.to_string(), bundle);
+        }
+        rosc::OscPacket::Bundle(b) => {
+            let id = match bundle {
+                Some((id, _)) => id,
+                None => NEXT_BUNDLE_ID.fetch_add(1, Ordering::Relaxed),
+            };
+            let timetag = wall_clock(b.timetag);
+            for content in b.content {
+                process_packet(content, tx, timestamp, sender, Some((id, timetag.clone())));
+            }
+        }
+    }
+}
+
+// NTP seconds wrap in 2036, so timetags that would fall before 1970 are read as the
+// next era. None for "immediately" (0x00000000_00000001).
+fn wall_clock(timetag: rosc::OscTime) -> Option<String> {
+    if timetag.seconds == 0 && timetag.fractional == 1 {
+        return None;
+    }
+    let mut seconds = timetag.seconds as i64 - NTP_UNIX_OFFSET as i64;
+    if seconds < 0 {
+        seconds += 1 << 32;
+    }
+    let nanos = ((timetag.fractional as u64 * 1_000_000_000) >> 32) as u32;
+    let time = DateTime::from_timestamp(seconds, nanos)?.with_timezone(&Local);
+    Some(time.format("%Y-%m-%d %H:%M:%S%.6f").to_string())
+}
+
+fn send_message(msg: &rosc::OscMessage, tx: &mpsc::Sender<OscData>, timestamp: &str, sender: String, bundle: Option<(u64, Option<String>)>) {
     tx.send(OscData {
-        message: OscMessageData {
-            address: msg.addr.clone(),
-            args,
-        },
+        message: msg.into(),
         timestamp: timestamp.to_string(),
         sender,
+        bundle_id: bundle.as_ref().map(|(id, _)| *id),
+        timetag: bundle.and_then(|(_, timetag)| timetag),
     }).unwrap();
-}
\ No newline at end of file
+}
@@ -1,22 +1,89 @@
-use serde::Serialize;
-use rosc;
+use std::time::{Duration, SystemTime};
+use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct OscMessageData {
     pub address: String,
     pub args: Vec<OscArgData>,
 }
 
-#[derive(Serialize, Clone, Debug)]
+// Covers every OSC 1.0 and 1.1 type tag; `type` names the tag in JSON.
+#[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(tag = "type", content = "value")]
 pub enum OscArgData {
+    // i
     Int(i32),
+    // f
     Float(f32),
+    // s and S
     String(String),
+    // b
     Blob(Vec<u8>),
+    // T and F
     Bool(bool),
+    // N
     Nil,
+    // I
     Inf,
+    // h
+    Long(i64),
+    // d
+    Double(f64),
+    // c
+    Char(char),
+    // r, 8 bits per component
+    Color { red: u8, green: u8, blue: u8, alpha: u8 },
+    // m
+    Midi { port: u8, status: u8, data1: u8, data2: u8 },
+    // t, as NTP seconds and 1/2^32 fractions
+    TimeTag { seconds: u32, fractional: u32 },
+    // [ ... ]
+    Array(Vec<OscArgData>),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum OscPacketData {
+    Message(OscMessageData),
+    Bundle(OscBundleData),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct OscBundleData {
+    pub timetag: OscTimeTagData,
+    pub content: Vec<OscPacketData>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type", content = "value", rename_all = "lowercase")]
+pub enum OscTimeTagData {
+    Immediate,
+    // Milliseconds after the packet is encoded.
+    Delay(u64),
+    Ntp { seconds: u32, fractional: u32 },
+}
+
+impl OscPacketData {
+    // Relative timetags are resolved against `now`, so a repeated bundle gets a fresh one each time.
+    pub fn to_packet(&self, now: SystemTime) -> Result<rosc::OscPacket, String> {
+        match self {
+            OscPacketData::Message(msg) => Ok(rosc::OscPacket::Message(msg.into())),
+            OscPacketData::Bundle(bundle) => {
+                let timetag = match bundle.timetag {
+                    // OSC 1.0 reserves 0x00000000_00000001 for "immediately".
+                    OscTimeTagData::Immediate => rosc::OscTime { seconds: 0, fractional: 1 },
+                    OscTimeTagData::Delay(ms) => (now + Duration::from_millis(ms))
+                        .try_into()
+                        .map_err(|e: rosc::OscTimeError| e.to_string())?,
+                    OscTimeTagData::Ntp { seconds, fractional } => rosc::OscTime { seconds, fractional },
+                };
+                let content = bundle.content.iter()
+                    .map(|packet| packet.to_packet(now))
+                    .collect::<Result<Vec<_>, String>>()?;
+                Ok(rosc::OscPacket::Bundle(rosc::OscBundle { timetag, content }))
+            }
+        }
+    }
 }
 
 impl From<&rosc::OscMessage> for OscMessageData {
@@ -38,7 +105,55 @@ impl From<&rosc::OscType> for OscArgData {
             rosc::OscType::Bool(b) => OscArgData::Bool(*b),
             rosc::OscType::Nil => OscArgData::Nil,
             rosc::OscType::Inf => OscArgData::Inf,
-            _ => unimplemented!("OSC type not supported"),
+            rosc::OscType::Long(l) => OscArgData::Long(*l),
+            rosc::OscType::Double(d) => OscArgData::Double(*d),
+            rosc::OscType::Char(c) => OscArgData::Char(*c),
+            rosc::OscType::Color(c) => OscArgData::Color { red: c.red, green: c.green, blue: c.blue, alpha: c.alpha },
+            rosc::OscType::Midi(m) => OscArgData::Midi { port: m.port, status: m.status, data1: m.data1, data2: m.data2 },
+            rosc::OscType::Time(t) => OscArgData::TimeTag { seconds: t.seconds, fractional: t.fractional },
+            rosc::OscType::Array(a) => OscArgData::Array(a.content.iter().map(|arg| arg.into()).collect()),
         }
     }
-}
\ No newline at end of file
+}
+
+impl From<&OscMessageData> for rosc::OscMessage {
+    fn from(msg: &OscMessageData) -> Self {
+        rosc::OscMessage {
+            addr: msg.address.clone(),
+            args: msg.args.iter().map(|arg| arg.into()).collect(),
+        }
+    }
+}
+
+impl From<&OscArgData> for rosc::OscType {
+    fn from(arg: &OscArgData) -> Self {
+        match arg {
+            OscArgData::Int(i) => rosc::OscType::Int(*i),
+            OscArgData::Float(f) => rosc::OscType::Float(*f),
+            OscArgData::String(s) => rosc::OscType::String(s.clone()),
+            OscArgData::Blob(b) => rosc::OscType::Blob(b.clone()),
+            OscArgData::Bool(b) => rosc::OscType::Bool(*b),
+            OscArgData::Nil => rosc::OscType::Nil,
+            OscArgData::Inf => rosc::OscType::Inf,
+            OscArgData::Long(l) => rosc::OscType::Long(*l),
+            OscArgData::Double(d) => rosc::OscType::Double(*d),
+            OscArgData::Char(c) => rosc::OscType::Char(*c),
+            OscArgData::Color { red, green, blue, alpha } => rosc::OscType::Color(rosc::OscColor {
+                red: *red,
+                green: *green,
+                blue: *blue,
+                alpha: *alpha,
+            }),
+            OscArgData::Midi { port, status, data1, data2 } => rosc::OscType::Midi(rosc::OscMidiMessage {
+                port: *port,
+                status: *status,
+                data1: *data1,
+                data2: *data2,
+            }),
+            OscArgData::TimeTag { seconds, fractional } => rosc::OscType::Time(rosc::OscTime { seconds: *seconds, fractional: *fractional }),
+            OscArgData::Array(a) => rosc::OscType::Array(rosc::OscArray {
+                content: a.iter().map(|arg| arg.into()).collect(),
+            }),
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::Mutex;
+
+use regex::Regex;
+
+use crate::osc_filter;
+use crate::osc_sender;
+use crate::protocols::{OscProxyConfig, OscRewriteRule};
+
+enum Rewrite {
+    StripPrefix(String),
+    AddPrefix(String),
+    Replace(Regex, String),
+    Scale {
+        address: Option<String>,
+        index: Option<usize>,
+        input: (f64, f64),
+        output: (f64, f64),
+    },
+}
+
+struct Proxy {
+    config: OscProxyConfig,
+    destinations: Vec<SocketAddr>,
+    rewrites: Vec<Rewrite>,
+}
+
+// Resends received packets. Without rewrite rules the original bytes go out
+// untouched; with them the packet is decoded, rewritten and encoded again.
+pub struct OscProxy {
+    socket: UdpSocket,
+    proxy: Mutex<Option<Proxy>>,
+}
+
+impl OscProxy {
+    pub fn new() -> Result<OscProxy, String> {
+        let socket = UdpSocket::bind("0.0.0.0:0").map_err(|e| e.to_string())?;
+        socket.set_broadcast(true).map_err(|e| e.to_string())?;
+        Ok(OscProxy { socket, proxy: Mutex::new(None) })
+    }
+
+    // `None` turns forwarding off.
+    pub fn configure(&self, config: Option<OscProxyConfig>) -> Result<(), String> {
+        let proxy = match config {
+            Some(config) => {
+                let destinations = config.destinations.iter()
+                    .map(|d| osc_sender::resolve(d))
+                    .collect::<Result<Vec<_>, String>>()?;
+                let rewrites = config.rewrites.iter()
+                    .map(compile)
+                    .collect::<Result<Vec<_>, String>>()?;
+                Some(Proxy { config, destinations, rewrites })
+            }
+            None => None,
+        };
+        *self.proxy.lock().unwrap() = proxy;
+        Ok(())
+    }
+
+    pub fn config(&self) -> Option<OscProxyConfig> {
+        self.proxy.lock().unwrap().as_ref().map(|p| p.config.clone())
+    }
+
+    pub fn forward(&self, bytes: &[u8]) {
+        let proxy = self.proxy.lock().unwrap();
+        let Some(ref proxy) = *proxy else {
+            return;
+        };
+
+        let rewritten;
+        let out = if proxy.rewrites.is_empty() {
+            bytes
+        } else {
+            let packet = match rosc::decoder::decode_udp(bytes) {
+                Ok((_, packet)) => packet,
+                // Not OSC as far as rosc can tell; pass it on as it came.
+                Err(_) => return self.send(&proxy.destinations, bytes),
+            };
+            match rosc::encoder::encode(&rewrite_packet(packet, &proxy.rewrites)) {
+                Ok(b) => {
+                    rewritten = b;
+                    &rewritten
+                }
+                Err(e) => {
+                    eprintln!("Failed to encode rewritten OSC packet: {}", e);
+                    return;
+                }
+            }
+        };
+        self.send(&proxy.destinations, out);
+    }
+
+    fn send(&self, destinations: &[SocketAddr], bytes: &[u8]) {
+        for addr in destinations {
+            if let Err(e) = self.socket.send_to(bytes, addr) {
+                eprintln!("Failed to forward OSC packet to {}: {}", addr, e);
+            }
+        }
+    }
+}
+
+fn compile(rule: &OscRewriteRule) -> Result<Rewrite, String> {
+    Ok(match rule {
+        OscRewriteRule::StripPrefix { prefix } => Rewrite::StripPrefix(prefix.clone()),
+        OscRewriteRule::AddPrefix { prefix } => Rewrite::AddPrefix(prefix.clone()),
+        OscRewriteRule::Replace { pattern, replacement } => {
+            let regex = Regex::new(pattern).map_err(|e| format!("Invalid pattern {}: {}", pattern, e))?;
+            Rewrite::Replace(regex, replacement.clone())
+        }
+        OscRewriteRule::Scale { address, index, in_min, in_max, out_min, out_max } => {
+            if in_min == in_max {
+                return Err("Scale input range must not be empty".to_string());
+            }
+            Rewrite::Scale {
+                address: address.clone(),
+                index: *index,
+                input: (*in_min, *in_max),
+                output: (*out_min, *out_max),
+            }
+        }
+    })
+}
+
+fn rewrite_packet(packet: rosc::OscPacket, rewrites: &[Rewrite]) -> rosc::OscPacket {
+    match packet {
+        rosc::OscPacket::Message(mut msg) => {
+            for rewrite in rewrites {
+                apply(rewrite, &mut msg);
+            }
+            rosc::OscPacket::Message(msg)
+        }
+        rosc::OscPacket::Bundle(bundle) => rosc::OscPacket::Bundle(rosc::OscBundle {
+            timetag: bundle.timetag,
+            content: bundle.content.into_iter().map(|p| rewrite_packet(p, rewrites)).collect(),
+        }),
+    }
+}
+
+fn apply(rewrite: &Rewrite, msg: &mut rosc::OscMessage) {
+    match rewrite {
+        Rewrite::StripPrefix(prefix) => {
+            // Whole address parts only, so "/eos" strips "/eos/out" but leaves "/eosx" alone.
+            if let Some(rest) = msg.addr.strip_prefix(prefix.trim_end_matches('/')) {
+                if rest.is_empty() {
+                    msg.addr = "/".to_string();
+                } else if rest.starts_with('/') {
+                    msg.addr = rest.to_string();
+                }
+            }
+        }
+        Rewrite::AddPrefix(prefix) => {
+            msg.addr = format!("{}{}", prefix.trim_end_matches('/'), msg.addr);
+        }
+        Rewrite::Replace(regex, replacement) => {
+            msg.addr = regex.replace_all(&msg.addr, replacement.as_str()).into_owned();
+        }
+        Rewrite::Scale { address, index, input, output } => {
+            if address.as_ref().is_some_and(|pattern| !osc_filter::pattern_matches(pattern, &msg.addr)) {
+                return;
+            }
+            let scale = |v: f64| output.0 + (v - input.0) * (output.1 - output.0) / (input.1 - input.0);
+            for (i, arg) in msg.args.iter_mut().enumerate() {
+                if index.is_some_and(|index| index != i) {
+                    continue;
+                }
+                // Arguments keep their type; integers are rounded.
+                *arg = match *arg {
+                    rosc::OscType::Int(v) => rosc::OscType::Int(scale(v as f64).round() as i32),
+                    rosc::OscType::Long(v) => rosc::OscType::Long(scale(v as f64).round() as i64),
+                    rosc::OscType::Float(v) => rosc::OscType::Float(scale(v as f64) as f32),
+                    rosc::OscType::Double(v) => rosc::OscType::Double(scale(v)),
+                    _ => continue,
+                };
+            }
+        }
+    }
+}
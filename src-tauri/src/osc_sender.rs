@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+use std::fs;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::path::PathBuf;
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::osc_message_data::OscPacketData;
+use crate::protocols::OscPreset;
+
+const MIN_REPEAT_INTERVAL: Duration = Duration::from_millis(10);
+
+pub struct OscSender {
+    socket: Arc<UdpSocket>,
+    // Dropping a repeat's stop channel ends its thread.
+    repeats: Mutex<HashMap<String, mpsc::Sender<()>>>,
+}
+
+impl OscSender {
+    pub fn new() -> Result<OscSender, String> {
+        let socket = UdpSocket::bind("0.0.0.0:0").map_err(|e| e.to_string())?;
+        socket.set_broadcast(true).map_err(|e| e.to_string())?;
+        Ok(OscSender {
+            socket: Arc::new(socket),
+            repeats: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn send(&self, target: &str, packet: &OscPacketData) -> Result<(), String> {
+        let addr = resolve(target)?;
+        send_packet(&self.socket, addr, packet)
+    }
+
+    // Sends once straight away so a bad target or packet is reported to the caller, then
+    // keeps sending every `interval` until stopped. An existing repeat with the same id is replaced.
+    pub fn start_repeat(&self, id: String, target: &str, packet: OscPacketData, interval: Duration) -> Result<(), String> {
+        if interval < MIN_REPEAT_INTERVAL {
+            return Err(format!("Repeat interval must be at least {} ms", MIN_REPEAT_INTERVAL.as_millis()));
+        }
+        let addr = resolve(target)?;
+        send_packet(&self.socket, addr, &packet)?;
+
+        let (stop_tx, stop_rx) = mpsc::channel::<()>();
+        let socket = self.socket.clone();
+        std::thread::spawn(move || {
+            let mut next = Instant::now() + interval;
+            while let Err(mpsc::RecvTimeoutError::Timeout) = stop_rx.recv_timeout(next.saturating_duration_since(Instant::now())) {
+                if let Err(e) = send_packet(&socket, addr, &packet) {
+                    eprintln!("Failed to send repeated OSC packet to {}: {}", addr, e);
+                }
+                next += interval;
+            }
+        });
+
+        self.repeats.lock().unwrap().insert(id, stop_tx);
+        Ok(())
+    }
+
+    pub fn stop_repeat(&self, id: &str) -> Result<(), String> {
+        match self.repeats.lock().unwrap().remove(id) {
+            Some(_) => Ok(()),
+            None => Err(format!("No repeat running with id {}", id)),
+        }
+    }
+
+    pub fn list_repeats(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self.repeats.lock().unwrap().keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+}
+
+// Only IPv4 targets are supported since the socket is bound to 0.0.0.0.
+pub fn resolve(target: &str) -> Result<SocketAddr, String> {
+    target.to_socket_addrs()
+        .map_err(|e| format!("Invalid OSC target {}: {}", target, e))?
+        .find(|addr| addr.is_ipv4())
+        .ok_or_else(|| format!("No IPv4 address found for {}", target))
+}
+
+fn send_packet(socket: &UdpSocket, addr: SocketAddr, packet: &OscPacketData) -> Result<(), String> {
+    let bytes = rosc::encoder::encode(&packet.to_packet(SystemTime::now())?).map_err(|e| e.to_string())?;
+    socket.send_to(&bytes, addr).map(|_| ()).map_err(|e| e.to_string())
+}
+
+// Saved messages, kept as pretty printed JSON so they can be edited or shared by hand.
+pub struct PresetStore {
+    path: PathBuf,
+    presets: Mutex<Vec<OscPreset>>,
+}
+
+impl PresetStore {
+    pub fn load(path: PathBuf) -> PresetStore {
+        let presets = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                eprintln!("Ignoring unreadable OSC presets file {}: {}", path.display(), e);
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        };
+        PresetStore {
+            path,
+            presets: Mutex::new(presets),
+        }
+    }
+
+    pub fn list(&self) -> Vec<OscPreset> {
+        self.presets.lock().unwrap().clone()
+    }
+
+    // Replaces any preset with the same name.
+    pub fn save(&self, preset: OscPreset) -> Result<(), String> {
+        if preset.name.is_empty() {
+            return Err("Preset name must not be empty".to_string());
+        }
+        let mut presets = self.presets.lock().unwrap();
+        match presets.iter_mut().find(|p| p.name == preset.name) {
+            Some(existing) => *existing = preset,
+            None => presets.push(preset),
+        }
+        self.write(&presets)
+    }
+
+    pub fn delete(&self, name: &str) -> Result<(), String> {
+        let mut presets = self.presets.lock().unwrap();
+        let before = presets.len();
+        presets.retain(|p| p.name != name);
+        if presets.len() == before {
+            return Err(format!("No preset named {}", name));
+        }
+        self.write(&presets)
+    }
+
+    fn write(&self, presets: &[OscPreset]) -> Result<(), String> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let contents = serde_json::to_string_pretty(presets).map_err(|e| e.to_string())?;
+        fs::write(&self.path, contents).map_err(|e| e.to_string())
+    }
+}
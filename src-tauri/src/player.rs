@@ -0,0 +1,272 @@
+use std::io::Write;
+use std::net::{Ipv4Addr, UdpSocket};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use crate::dmx_sender::{ArtnetSender, SacnSender};
+use crate::protocols::{DmxSource, PlayerCommand, PlayerStatus, ReplayOutputs};
+use crate::recorder::{CaptureData, CaptureReader};
+
+// Upper bound on how long the player sleeps, so commands and status stay responsive.
+const MAX_WAIT: Duration = Duration::from_millis(50);
+const STATUS_INTERVAL: Duration = Duration::from_millis(250);
+const MAX_SPEED: f64 = 16.0;
+
+struct Outputs {
+    osc_socket: Option<UdpSocket>,
+    osc_target: Option<String>,
+    sacn: Option<SacnSender>,
+    sacn_unicast: Option<Ipv4Addr>,
+    artnet: Option<(ArtnetSender, Ipv4Addr)>,
+    serial: Option<Box<dyn serialport::SerialPort>>,
+}
+
+impl Outputs {
+    fn none() -> Outputs {
+        Outputs {
+            osc_socket: None,
+            osc_target: None,
+            sacn: None,
+            sacn_unicast: None,
+            artnet: None,
+            serial: None,
+        }
+    }
+
+    fn open(config: ReplayOutputs) -> Outputs {
+        let mut outputs = Outputs::none();
+
+        if let Some(target) = config.osc_target {
+            match UdpSocket::bind("0.0.0.0:0") {
+                Ok(s) => {
+                    outputs.osc_socket = Some(s);
+                    outputs.osc_target = Some(target);
+                }
+                Err(e) => eprintln!("Failed to open OSC replay socket: {}", e),
+            }
+        }
+
+        if config.sacn_enabled {
+            match SacnSender::new("fluxviewer replay", None) {
+                Ok(s) => outputs.sacn = Some(s),
+                Err(e) => eprintln!("Failed to open sACN replay source: {}", e),
+            }
+            outputs.sacn_unicast = config.sacn_unicast.and_then(|ip| ip.parse().ok());
+        }
+
+        if let Some(target) = config.artnet_target {
+            match (ArtnetSender::new(), target.parse::<Ipv4Addr>()) {
+                (Ok(s), Ok(ip)) => outputs.artnet = Some((s, ip)),
+                (Err(e), _) => eprintln!("Failed to open Art-Net replay socket: {}", e),
+                (_, Err(e)) => eprintln!("Invalid Art-Net replay target {}: {}", target, e),
+            }
+        }
+
+        if let Some(port_name) = config.serial_port {
+            match serialport::new(&port_name, config.serial_baud_rate)
+                .timeout(Duration::from_millis(100))
+                .open()
+            {
+                Ok(p) => outputs.serial = Some(p),
+                Err(e) => eprintln!("Failed to open serial replay port {}: {}", port_name, e),
+            }
+        }
+
+        outputs
+    }
+
+    fn send(&mut self, data: &CaptureData) {
+        match data {
+            CaptureData::Osc(osc) => {
+                if let (Some(ref s), Some(ref target)) = (&self.osc_socket, &self.osc_target) {
+                    let packet = rosc::OscPacket::Message((&osc.message).into());
+                    match rosc::encoder::encode(&packet) {
+                        Ok(bytes) => {
+                            if let Err(e) = s.send_to(&bytes, target) {
+                                eprintln!("Failed to replay OSC to {}: {}", target, e);
+                            }
+                        }
+                        Err(e) => eprintln!("Failed to encode OSC message {}: {}", osc.message.address, e),
+                    }
+                }
+            }
+            CaptureData::Sacn(dmx) => {
+                if let Some(ref mut sender) = self.sacn {
+                    let priority = match dmx.source {
+                        Some(DmxSource::Sacn(ref info)) => Some(info.priority),
+                        _ => None,
+                    };
+                    if let Err(e) = sender.send_start_code(dmx.universe, dmx.start_code, &dmx.channels, priority, self.sacn_unicast) {
+                        eprintln!("Failed to replay sACN universe {}: {}", dmx.universe, e);
+                    }
+                }
+            }
+            CaptureData::Artnet(dmx) => {
+                if let Some((ref mut sender, destination)) = self.artnet {
+                    if let Err(e) = sender.send(dmx.universe, &dmx.channels, destination) {
+                        eprintln!("Failed to replay Art-Net universe {}: {}", dmx.universe, e);
+                    }
+                }
+            }
+            CaptureData::Serial(serial) => {
+                if let Some(ref mut p) = self.serial {
+                    if let Err(e) = p.write_all(&serial.bytes) {
+                        eprintln!("Failed to replay serial data: {}", e);
+                    }
+                }
+            }
+            // Widget input is a hard-wired line with no network output to replay onto.
+            CaptureData::Enttec(_) => {}
+        }
+    }
+}
+
+pub fn start(command_rx: mpsc::Receiver<PlayerCommand>, status_tx: mpsc::Sender<PlayerStatus>) {
+    let mut capture: Option<CaptureReader> = None;
+    let mut path: Option<String> = None;
+    let mut outputs = Outputs::none();
+    let mut playing = false;
+    let mut speed = 1.0f64;
+    let mut looping = false;
+    // Capture position at `anchor_time`; playback position is extrapolated from it.
+    let mut anchor_offset: u64 = 0;
+    let mut anchor_time = Instant::now();
+    let mut last_status = Instant::now();
+
+    loop {
+        let position = playback_position(playing, anchor_offset, anchor_time, speed);
+
+        let wait = match capture.as_ref().and_then(|c| c.peek_offset()) {
+            Some(next) if playing && next > position => {
+                Duration::from_nanos(((next - position) as f64 / speed) as u64).min(MAX_WAIT)
+            }
+            Some(_) if playing => Duration::ZERO,
+            _ => MAX_WAIT,
+        };
+
+        let mut status_changed = false;
+        match command_rx.recv_timeout(wait) {
+            Ok(PlayerCommand::Load { path: new_path }) => {
+                playing = false;
+                anchor_offset = 0;
+                match CaptureReader::open(&new_path) {
+                    Ok(c) => {
+                        eprintln!("Loaded capture {} ({} frames)", new_path, c.frame_count());
+                        capture = Some(c);
+                        path = Some(new_path);
+                    }
+                    Err(e) => {
+                        eprintln!("Failed to load capture {}: {}", new_path, e);
+                        capture = None;
+                        path = None;
+                    }
+                }
+                status_changed = true;
+            }
+            Ok(PlayerCommand::SetOutputs(config)) => {
+                outputs = Outputs::open(config);
+            }
+            Ok(PlayerCommand::Play) => {
+                if let Some(c) = capture.as_mut().filter(|_| !playing) {
+                    // Playing again after reaching the end starts over, like a media player.
+                    if c.peek_offset().is_none() {
+                        c.seek(0);
+                        anchor_offset = 0;
+                    }
+                    playing = true;
+                    anchor_time = Instant::now();
+                    status_changed = true;
+                }
+            }
+            Ok(PlayerCommand::Pause) => {
+                if playing {
+                    anchor_offset = playback_position(playing, anchor_offset, anchor_time, speed);
+                    playing = false;
+                    status_changed = true;
+                }
+            }
+            Ok(PlayerCommand::Stop) => {
+                playing = false;
+                anchor_offset = 0;
+                if let Some(ref mut c) = capture {
+                    c.seek(0);
+                }
+                status_changed = true;
+            }
+            Ok(PlayerCommand::Seek { position_ms }) => {
+                let duration = capture.as_ref().map_or(0, |c| c.duration_ns());
+                anchor_offset = position_ms.saturating_mul(1_000_000).min(duration);
+                anchor_time = Instant::now();
+                if let Some(ref mut c) = capture {
+                    c.seek(anchor_offset);
+                }
+                status_changed = true;
+            }
+            Ok(PlayerCommand::SetSpeed(new_speed)) => {
+                if check_speed(new_speed).is_ok() {
+                    anchor_offset = playback_position(playing, anchor_offset, anchor_time, speed);
+                    anchor_time = Instant::now();
+                    speed = new_speed;
+                    status_changed = true;
+                }
+            }
+            Ok(PlayerCommand::SetLoop(new_looping)) => {
+                looping = new_looping;
+                status_changed = true;
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => return,
+        }
+
+        if playing {
+            if let Some(ref mut c) = capture {
+                let position = playback_position(playing, anchor_offset, anchor_time, speed);
+                while c.peek_offset().is_some_and(|next| next <= position) {
+                    if let Some(data) = c.next_frame() {
+                        outputs.send(&data);
+                    }
+                }
+
+                if c.peek_offset().is_none() {
+                    if looping {
+                        c.seek(0);
+                        anchor_offset = 0;
+                        anchor_time = Instant::now();
+                    } else {
+                        playing = false;
+                        anchor_offset = c.duration_ns();
+                    }
+                    status_changed = true;
+                }
+            }
+        }
+
+        if status_changed || (playing && last_status.elapsed() >= STATUS_INTERVAL) {
+            let position = playback_position(playing, anchor_offset, anchor_time, speed);
+            status_tx.send(PlayerStatus {
+                path: path.clone(),
+                playing,
+                looping,
+                speed,
+                position_ms: position / 1_000_000,
+                duration_ms: capture.as_ref().map(|c| c.duration_ns() / 1_000_000).unwrap_or(0),
+            }).ok();
+            last_status = Instant::now();
+        }
+    }
+}
+
+fn playback_position(playing: bool, anchor_offset: u64, anchor_time: Instant, speed: f64) -> u64 {
+    if playing {
+        anchor_offset.saturating_add((anchor_time.elapsed().as_nanos() as f64 * speed) as u64)
+    } else {
+        anchor_offset
+    }
+}
+
+pub fn check_speed(speed: f64) -> Result<(), String> {
+    if !(speed > 0.0 && speed <= MAX_SPEED) {
+        return Err(format!("Speed must be above 0 and at most {}x", MAX_SPEED));
+    }
+    Ok(())
+}
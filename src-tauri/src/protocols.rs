@@ -1,21 +1,264 @@
-use serde::Serialize;
-use crate::osc_message_data::OscMessageData;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use crate::osc_message_data::{OscMessageData, OscPacketData};
 
+pub const START_CODE_LEVELS: u8 = 0x00;
+pub const START_CODE_ADDRESS_PRIORITY: u8 = 0xDD;
 
-#[derive(Serialize, Clone)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct DmxData {
     pub universe: u16,
     pub channels: Vec<u8>,
+    pub source: Option<DmxSource>,
+    // 0x00 for levels. sACN also carries 0xDD per-address priority and other
+    // alternate start codes such as RDM or text, whose slots aren't levels.
+    #[serde(default)]
+    pub start_code: u8,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "protocol", rename_all = "lowercase")]
+pub enum DmxSource {
+    Sacn(SacnSourceInfo),
+    Artnet(ArtnetSourceInfo),
+    Enttec(EnttecSourceInfo),
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SacnSourceInfo {
+    pub cid: String,
+    pub name: String,
+    pub priority: u8,
+    pub sequence: u8,
+    pub preview: bool,
+    pub terminated: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ArtnetSourceInfo {
+    pub sender: String,
+    pub sequence: u8,
+    pub physical: u8,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct EnttecSourceInfo {
+    pub port: String,
+    pub serial_number: String,
+}
+
+#[derive(Serialize, Clone)]
+pub struct EnttecWidgetInfo {
+    pub port: String,
+    pub serial_number: String,
+    pub firmware: String,
+    pub break_time_us: f64,
+    pub mab_time_us: f64,
+    pub refresh_rate: u8,
+}
+
+#[derive(Serialize, Clone)]
+pub struct SacnSourceStatus {
+    pub cid: String,
+    pub name: String,
+    pub priority: u8,
+    pub last_seen: String,
 }
 
 #[derive(Serialize, Clone)]
+pub struct SacnUniverseSources {
+    pub universe: u16,
+    pub sources: Vec<SacnSourceStatus>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
 pub struct OscData {
     pub message: OscMessageData,
     pub timestamp: String,
     pub sender: String,
+    // Shared by every message that arrived in the same top-level bundle.
+    #[serde(default)]
+    pub bundle_id: Option<u64>,
+    // Wall-clock time of the innermost bundle's timetag; None for plain
+    // messages and bundles tagged "immediately".
+    #[serde(default)]
+    pub timetag: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct MidiData {
+    pub timestamp: String,
+    pub source: String,
+    pub message: MidiMessage,
+    pub bytes: Vec<u8>,
+}
+
+// Channels are reported 1-16 as shown on consoles and instruments.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MidiMessage {
+    NoteOff { channel: u8, note: u8, velocity: u8 },
+    NoteOn { channel: u8, note: u8, velocity: u8 },
+    PolyPressure { channel: u8, note: u8, pressure: u8 },
+    ControlChange { channel: u8, controller: u8, value: u8 },
+    ProgramChange { channel: u8, program: u8 },
+    ChannelPressure { channel: u8, pressure: u8 },
+    // Centred on 0, from -8192 to 8191.
+    PitchBend { channel: u8, value: i16 },
+    // Payload between the F0 and F7 bytes.
+    SysEx { data: Vec<u8> },
+    // System common and real-time messages.
+    System { status: u8, data: Vec<u8> },
+    // MIDI Show Control. Cue number, list and path are ASCII such as "12.5".
+    ShowControl {
+        device_id: u8,
+        command_format: MscCommandFormat,
+        command: MscCommand,
+        cue: Option<String>,
+        list: Option<String>,
+        path: Option<String>,
+    },
+    // MTC quarter frame; `timecode` is the running value once enough pieces have arrived.
+    TimecodeQuarterFrame { piece: u8, value: u8, timecode: Option<Timecode> },
+    TimecodeFullFrame { device_id: u8, timecode: Timecode },
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MscCommandFormat {
+    Lighting,
+    MovingLights,
+    ColorChangers,
+    Strobes,
+    Lasers,
+    Chasers,
+    Sound,
+    Music,
+    CdPlayers,
+    EpromPlayback,
+    AudioTapeMachines,
+    Intercoms,
+    Amplifiers,
+    AudioEffects,
+    Equalizers,
+    Machinery,
+    Rigging,
+    Flys,
+    Lifts,
+    Turntables,
+    Trusses,
+    Robots,
+    Animation,
+    Floats,
+    Breakaways,
+    Barges,
+    Video,
+    VideoTapeMachines,
+    VideoCassetteMachines,
+    VideoDiscPlayers,
+    VideoSwitchers,
+    VideoEffects,
+    VideoCharacterGenerators,
+    VideoStillStores,
+    VideoMonitors,
+    Projection,
+    FilmProjectors,
+    SlideProjectors,
+    VideoProjectors,
+    Dissolvers,
+    ShutterControls,
+    ProcessControl,
+    HydraulicOil,
+    H2o,
+    Co2,
+    CompressedAir,
+    NaturalGas,
+    Fog,
+    Smoke,
+    CrackedHaze,
+    Pyro,
+    Fireworks,
+    Explosions,
+    Flame,
+    SmokePots,
+    AllTypes,
+    Other(u8),
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MscCommand {
+    Go,
+    Stop,
+    Resume,
+    TimedGo,
+    Load,
+    Set,
+    Fire,
+    AllOff,
+    Restore,
+    Reset,
+    GoOff,
+    GoJamClock,
+    StandbyPlus,
+    StandbyMinus,
+    SequencePlus,
+    SequenceMinus,
+    StartClock,
+    StopClock,
+    ZeroClock,
+    SetClock,
+    MtcChaseOn,
+    MtcChaseOff,
+    OpenCueList,
+    CloseCueList,
+    OpenCuePath,
+    CloseCuePath,
+    Other(u8),
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct Timecode {
+    pub hours: u8,
+    pub minutes: u8,
+    pub seconds: u8,
+    pub frames: u8,
+    pub rate: TimecodeRate,
+}
+
+// One frame of timecode from any source. The counters are filled in by the
+// tracker the events pass through; sources leave them at zero.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TimecodeData {
+    pub timestamp: String,
+    pub source: String,
+    pub timecode: Timecode,
+    // Positive when the timecode runs ahead of the local clock.
+    pub drift_ms: f64,
+    pub dropouts: u64,
+    pub skipped_frames: u64,
+    pub jumps: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TimecodeRate {
+    // 24 fps
+    Film,
+    // 25 fps
+    Ebu,
+    // 29.97 fps drop frame
+    DropFrame,
+    // 30 fps
+    Smpte,
 }
 
 #[derive(Serialize, Clone)]
+pub struct MidiPortInfo {
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
 pub struct SerialData {
     pub timestamp: String,
     pub bytes: Vec<u8>,
@@ -23,15 +266,276 @@ pub struct SerialData {
     pub ascii: String,
 }
 
+#[derive(Serialize, Clone)]
+pub struct SacnDiscoveredSource {
+    pub cid: String,
+    pub name: String,
+    pub universes: Vec<u16>,
+    pub last_seen: String,
+}
+
+#[derive(Serialize, Clone)]
+pub struct ArtnetNode {
+    pub ip: String,
+    pub bind_index: u8,
+    pub short_name: String,
+    pub long_name: String,
+    pub node_report: String,
+    pub esta_code: u16,
+    pub oem: u16,
+    pub firmware: u16,
+    pub mac: String,
+    pub input_universes: Vec<u16>,
+    pub output_universes: Vec<u16>,
+    pub status_1: u8,
+    pub status_2: u8,
+    pub last_seen: String,
+}
+
 #[derive(Serialize, Clone)]
 pub struct SerialPortInfo {
     pub name: String,
     pub description: String,
 }
 
+#[derive(Serialize, Clone)]
+pub struct StreamStats {
+    pub protocol: String,
+    pub universe: Option<u16>,
+    pub source: String,
+    pub source_name: String,
+    pub fps: f64,
+    pub interval_min_ms: f64,
+    pub interval_avg_ms: f64,
+    pub interval_max_ms: f64,
+    pub interval_stddev_ms: f64,
+    pub window_frames: u64,
+    pub window_lost: u64,
+    pub window_out_of_order: u64,
+    pub total_frames: u64,
+    pub total_lost: u64,
+    pub total_out_of_order: u64,
+    pub last_seen: String,
+}
+
+#[derive(Serialize, Clone)]
+pub struct RecorderStatus {
+    pub recording: bool,
+    pub path: Option<String>,
+    pub started_at: Option<String>,
+    pub duration_ms: u64,
+    pub records: u64,
+}
+
+#[derive(Serialize, Clone)]
+pub struct PlayerStatus {
+    pub path: Option<String>,
+    pub playing: bool,
+    pub looping: bool,
+    pub speed: f64,
+    pub position_ms: u64,
+    pub duration_ms: u64,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct ReplayOutputs {
+    pub osc_target: Option<String>,
+    pub sacn_enabled: bool,
+    pub sacn_unicast: Option<String>,
+    pub artnet_target: Option<String>,
+    pub serial_port: Option<String>,
+    pub serial_baud_rate: u32,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct DmxOutputConfig {
+    pub sacn_enabled: bool,
+    pub sacn_cid: Option<String>,
+    pub sacn_source_name: String,
+    pub sacn_priority: u8,
+    pub sacn_unicast: Option<String>,
+    pub artnet_target: Option<String>,
+    pub refresh_rate: f64,
+}
+
+#[derive(Serialize, Clone)]
+pub struct DmxOutputUniverse {
+    pub universe: u16,
+    pub levels: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum DmxBridgeDirection {
+    ArtnetToSacn,
+    SacnToArtnet,
+}
+
+// Art-Net universes are 15-bit port addresses, i.e. net << 8 | sub-net << 4 | universe.
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct DmxBridgeRoute {
+    pub direction: DmxBridgeDirection,
+    pub input: u16,
+    pub output: u16,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct DmxBridgeConfig {
+    pub routes: Vec<DmxBridgeRoute>,
+    pub sacn_source_name: String,
+    pub sacn_cid: Option<String>,
+    pub sacn_priority: u8,
+    pub sacn_unicast: Option<String>,
+    // Needed for sACN to Art-Net routes; a node's address or a broadcast address.
+    pub artnet_target: Option<String>,
+    pub refresh_rate: f64,
+}
+
+#[derive(Serialize, Clone)]
+pub struct DmxBridgeRouteStatus {
+    pub direction: DmxBridgeDirection,
+    pub input: u16,
+    pub output: u16,
+    pub active: bool,
+    pub last_input: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DmxMergeMode {
+    #[default]
+    Htp,
+    Ltp,
+}
+
+#[derive(Serialize, Clone)]
+pub struct DmxMergeSource {
+    // "sacn:<cid>" or "artnet:<sender ip>".
+    pub key: String,
+    pub name: String,
+    pub priority: u8,
+    // Present while the source is sending 0xDD per-address priority.
+    pub address_priorities: Option<Vec<u8>>,
+    pub levels: Vec<u8>,
+    // Number of channels this source currently drives in the merged output.
+    pub channels_won: usize,
+    pub last_seen: String,
+}
+
+#[derive(Serialize, Clone)]
+pub struct DmxMergeData {
+    pub protocol: String,
+    pub universe: u16,
+    pub mode: DmxMergeMode,
+    pub levels: Vec<u8>,
+    // Per channel, the index into `sources` of the source that won it.
+    pub owners: Vec<Option<usize>>,
+    pub sources: Vec<DmxMergeSource>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct OscPreset {
+    pub name: String,
+    pub target: String,
+    pub packet: OscPacketData,
+    pub interval_ms: Option<u64>,
+}
+
+#[derive(Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SerialParity {
+    #[default]
+    None,
+    Odd,
+    Even,
+}
+
+#[derive(Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SerialFlowControl {
+    #[default]
+    None,
+    Software,
+    Hardware,
+}
+
+// Missing fields fall back to 8N1 without flow control, leaving DTR/RTS as the driver set them.
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct SerialLineConfig {
+    pub data_bits: u8,
+    pub parity: SerialParity,
+    pub stop_bits: u8,
+    pub flow_control: SerialFlowControl,
+    pub dtr: Option<bool>,
+    pub rts: Option<bool>,
+}
+
+impl Default for SerialLineConfig {
+    fn default() -> Self {
+        SerialLineConfig {
+            data_bits: 8,
+            parity: SerialParity::None,
+            stop_bits: 1,
+            flow_control: SerialFlowControl::None,
+            dtr: None,
+            rts: None,
+        }
+    }
+}
+
+// How received bytes are grouped into `SerialData` events.
+#[derive(Deserialize, Clone, Default)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum SerialFraming {
+    // One event per read, whatever the driver returned.
+    #[default]
+    Raw,
+    Delimiter { delimiter: Vec<u8>, #[serde(default)] keep_delimiter: bool },
+    // Ends a frame once the line has been quiet for `timeout_ms`.
+    Idle { timeout_ms: u64 },
+    Fixed { length: usize },
+    // A 1, 2 or 4 byte length header; the emitted frame includes the header.
+    LengthPrefix { width: u8, #[serde(default)] little_endian: bool, #[serde(default)] includes_header: bool },
+    Slip,
+    Cobs,
+}
+
+#[derive(Serialize, Clone, PartialEq)]
+pub struct SerialModemLines {
+    pub cts: bool,
+    pub dsr: bool,
+    pub ri: bool,
+    pub cd: bool,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum SerialPayloadFormat {
+    Hex,
+    Ascii,
+    File,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEnding {
+    None,
+    Cr,
+    Lf,
+    Crlf,
+}
+
 pub enum SerialCommand {
-    Start { port: String, baud_rate: u32 },
+    Start { port: String, baud_rate: u32, config: SerialLineConfig },
     Stop,
+    SetDtr(bool),
+    SetRts(bool),
+    SetFraming(SerialFraming),
+    // Also decodes the received bytes as MIDI, for DIN-MIDI to serial adapters.
+    SetMidiDecoding(bool),
+    // Sends `bytes` `count` times, `interval` apart.
+    Write { bytes: Vec<u8>, count: u32, interval: Duration },
 }
 
 pub enum SacnCommand{
@@ -39,16 +543,143 @@ pub enum SacnCommand{
     Stop,
     SubscribeUniverse(u16),
     UnsubscribeUniverse(u16),
+    SubscribeDiscovered,
 }
 
 pub enum OscCommand {
-    Start { ip: String, port: u16 },
+    // For TCP clients `ip` and `port` are the server to connect to.
+    Start { ip: String, port: u16, transport: OscTransport },
     Stop,
 }
 
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum OscTransport {
+    #[default]
+    Udp,
+    TcpServer { framing: OscTcpFraming },
+    TcpClient { framing: OscTcpFraming },
+}
+
+// How packets are delimited on a TCP stream.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum OscTcpFraming {
+    // OSC 1.0: each packet preceded by its length as a big-endian int32.
+    LengthPrefix,
+    // OSC 1.1: SLIP encoded packets.
+    Slip,
+}
+
+// `address` is an OSC address pattern and `sender` an address or CIDR range;
+// a rule matches when every field it sets matches.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct OscFilterRule {
+    pub action: OscFilterAction,
+    #[serde(default)]
+    pub address: Option<String>,
+    #[serde(default)]
+    pub sender: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum OscFilterAction {
+    Allow,
+    Block,
+}
+
+// Forwards received packets to `destinations` (host:port, over UDP), applying
+// `rewrites` in order to every message, inside bundles too.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct OscProxyConfig {
+    pub destinations: Vec<String>,
+    #[serde(default)]
+    pub rewrites: Vec<OscRewriteRule>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OscRewriteRule {
+    // Addresses without the prefix are left alone.
+    StripPrefix { prefix: String },
+    AddPrefix { prefix: String },
+    // Regular expression replace; `replacement` may use $1 style groups.
+    Replace { pattern: String, replacement: String },
+    // Maps numeric arguments linearly from the input to the output range.
+    // `address` is an OSC address pattern (all messages when unset) matched
+    // after earlier rules, and `index` picks one argument (all when unset).
+    Scale {
+        #[serde(default)]
+        address: Option<String>,
+        #[serde(default)]
+        index: Option<usize>,
+        in_min: f64,
+        in_max: f64,
+        out_min: f64,
+        out_max: f64,
+    },
+}
+
+#[derive(Serialize, Clone)]
+pub struct OscConnection {
+    pub peer: String,
+    pub local: String,
+    pub connected_at: String,
+}
+
 pub enum ArtnetCommand {
     Start { ip: String },
     Stop,
     SubscribeUniverse(u16),
     UnsubscribeUniverse(u16),
-}
\ No newline at end of file
+    StartDiscovery { broadcast_ip: String },
+    StopDiscovery,
+}
+
+pub enum DmxOutputCommand {
+    Configure(DmxOutputConfig),
+    // Channels are 1-based; setting a level on a universe starts transmitting it.
+    SetChannel { universe: u16, channel: u16, value: u8 },
+    SetRange { universe: u16, start: u16, end: u16, value: u8 },
+    SetUniverse { universe: u16, levels: Vec<u8> },
+    ReleaseUniverse(u16),
+    Stop,
+}
+
+pub enum DmxBridgeCommand {
+    Configure(DmxBridgeConfig),
+    // Merged universes from the sACN and Art-Net listeners; the bridge picks out its inputs.
+    Input(DmxMergeData),
+    Stop,
+}
+
+pub enum MidiCommand {
+    Start { port: String },
+    // Creates a port other applications can connect to; not available on Windows.
+    StartVirtual { name: String },
+    Stop,
+}
+
+pub enum EnttecCommand {
+    // `on_change` asks the widget to only report channels that changed.
+    Start { port: String, universe: u16, on_change: bool },
+    Stop,
+}
+
+pub enum LtcCommand {
+    // Reads WAV audio from `path`, or from stdin when there is none.
+    Start { path: Option<String> },
+    Stop,
+}
+
+pub enum PlayerCommand {
+    Load { path: String },
+    SetOutputs(ReplayOutputs),
+    Play,
+    Pause,
+    Stop,
+    Seek { position_ms: u64 },
+    SetSpeed(f64),
+    SetLoop(bool),
+}
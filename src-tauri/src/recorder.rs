@@ -0,0 +1,253 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Seek, SeekFrom, Write};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use chrono::{DateTime, Local, SecondsFormat};
+use serde::{Deserialize, Serialize};
+
+use crate::protocols::{DmxData, OscData, RecorderStatus, SerialData};
+
+pub const CAPTURE_FORMAT: &str = "fluxviewer-capture";
+pub const CAPTURE_VERSION: u32 = 1;
+
+// Buffered records are pushed to disk at least this often so a crash loses little.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "protocol", content = "data", rename_all = "lowercase")]
+pub enum CaptureData {
+    Osc(OscData),
+    Sacn(DmxData),
+    Artnet(DmxData),
+    Serial(SerialData),
+    Enttec(DmxData),
+}
+
+#[derive(Serialize, Deserialize)]
+struct CaptureHeader<'a> {
+    format: &'a str,
+    version: u32,
+    started_at: String,
+}
+
+// Each line of a capture file after the header. `offset_ns` comes from a monotonic
+// clock and is authoritative; `time` is derived from it for reading the file by eye.
+#[derive(Serialize)]
+struct CaptureRecord<'a> {
+    offset_ns: u64,
+    time: String,
+    #[serde(flatten)]
+    data: &'a CaptureData,
+}
+
+struct ActiveCapture {
+    writer: BufWriter<File>,
+    path: String,
+    started: Instant,
+    started_at: DateTime<Local>,
+    last_flush: Instant,
+    records: u64,
+}
+
+#[derive(Default)]
+pub struct Recorder {
+    capture: Mutex<Option<ActiveCapture>>,
+}
+
+impl Recorder {
+    pub fn start(&self, path: String) -> Result<(), String> {
+        let mut capture = self.capture.lock().unwrap();
+        if let Some(ref active) = *capture {
+            return Err(format!("Already recording to {}", active.path));
+        }
+
+        let file = File::create(&path).map_err(|e| e.to_string())?;
+        let mut writer = BufWriter::new(file);
+        let started_at = Local::now();
+        let header = CaptureHeader {
+            format: CAPTURE_FORMAT,
+            version: CAPTURE_VERSION,
+            started_at: started_at.to_rfc3339_opts(SecondsFormat::Micros, false),
+        };
+        serde_json::to_writer(&mut writer, &header).map_err(|e| e.to_string())?;
+        writer.write_all(b"\n").map_err(|e| e.to_string())?;
+
+        eprintln!("Recording started: {}", path);
+        *capture = Some(ActiveCapture {
+            writer,
+            path,
+            started: Instant::now(),
+            started_at,
+            last_flush: Instant::now(),
+            records: 0,
+        });
+        Ok(())
+    }
+
+    pub fn stop(&self) -> Result<(), String> {
+        let active = self.capture.lock().unwrap().take();
+        match active {
+            Some(mut active) => {
+                active.writer.flush().map_err(|e| e.to_string())?;
+                eprintln!("Recording stopped: {} ({} records)", active.path, active.records);
+                Ok(())
+            }
+            None => Err("Not recording".to_string()),
+        }
+    }
+
+    pub fn status(&self) -> RecorderStatus {
+        match *self.capture.lock().unwrap() {
+            Some(ref active) => RecorderStatus {
+                recording: true,
+                path: Some(active.path.clone()),
+                started_at: Some(active.started_at.format("%H:%M:%S%.3f").to_string()),
+                duration_ms: active.started.elapsed().as_millis() as u64,
+                records: active.records,
+            },
+            None => RecorderStatus {
+                recording: false,
+                path: None,
+                started_at: None,
+                duration_ms: 0,
+                records: 0,
+            },
+        }
+    }
+
+    // `make` is only called while a capture is running so idle forwarding threads don't clone.
+    pub fn record(&self, make: impl FnOnce() -> CaptureData) {
+        let mut capture = self.capture.lock().unwrap();
+        let Some(ref mut active) = *capture else {
+            return;
+        };
+
+        let offset = active.started.elapsed();
+        let time = active.started_at + offset;
+        let data = make();
+        let record = CaptureRecord {
+            offset_ns: offset.as_nanos() as u64,
+            time: time.to_rfc3339_opts(SecondsFormat::Micros, false),
+            data: &data,
+        };
+
+        let result = serde_json::to_writer(&mut active.writer, &record)
+            .map_err(|e| e.to_string())
+            .and_then(|_| active.writer.write_all(b"\n").map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            eprintln!("Failed to write capture record to {}: {}", active.path, e);
+            return;
+        }
+        active.records += 1;
+
+        if active.last_flush.elapsed() >= FLUSH_INTERVAL {
+            if let Err(e) = active.writer.flush() {
+                eprintln!("Failed to flush capture file {}: {}", active.path, e);
+            }
+            active.last_flush = Instant::now();
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct CapturedFrame {
+    #[serde(flatten)]
+    data: CaptureData,
+}
+
+#[derive(Deserialize)]
+struct CaptureOffset {
+    offset_ns: u64,
+}
+
+// Reads a capture file back frame by frame. Only the offset and file position of
+// each record is kept in memory so multi-hour captures can be seeked cheaply.
+pub struct CaptureReader {
+    reader: BufReader<File>,
+    index: Vec<(u64, u64)>,
+    next: usize,
+    position: u64,
+    line: String,
+}
+
+impl CaptureReader {
+    pub fn open(path: &str) -> Result<CaptureReader, String> {
+        let file = File::open(path).map_err(|e| e.to_string())?;
+        let mut reader = BufReader::new(file);
+        let mut line = String::new();
+
+        reader.read_line(&mut line).map_err(|e| e.to_string())?;
+        let header: CaptureHeader = serde_json::from_str(&line)
+            .map_err(|_| format!("{} is not a capture file", path))?;
+        if header.format != CAPTURE_FORMAT || header.version > CAPTURE_VERSION {
+            return Err(format!("Unsupported capture format {} v{}", header.format, header.version));
+        }
+
+        let mut index = Vec::new();
+        let mut position = line.len() as u64;
+        loop {
+            line.clear();
+            let read = reader.read_line(&mut line).map_err(|e| e.to_string())?;
+            if read == 0 {
+                break;
+            }
+            if let Ok(record) = serde_json::from_str::<CaptureOffset>(&line) {
+                index.push((record.offset_ns, position));
+            }
+            position += read as u64;
+        }
+
+        Ok(CaptureReader {
+            reader,
+            index,
+            next: 0,
+            position,
+            line,
+        })
+    }
+
+    pub fn duration_ns(&self) -> u64 {
+        self.index.last().map(|(offset, _)| *offset).unwrap_or(0)
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.index.len()
+    }
+
+    // Positions the reader on the first frame at or after `offset_ns`.
+    pub fn seek(&mut self, offset_ns: u64) {
+        self.next = self.index.partition_point(|(offset, _)| *offset < offset_ns);
+    }
+
+    pub fn peek_offset(&self) -> Option<u64> {
+        self.index.get(self.next).map(|(offset, _)| *offset)
+    }
+
+    pub fn next_frame(&mut self) -> Option<CaptureData> {
+        while let Some(&(_, position)) = self.index.get(self.next) {
+            self.next += 1;
+            if position != self.position {
+                if let Err(e) = self.reader.seek(SeekFrom::Start(position)) {
+                    eprintln!("Error seeking capture file: {}", e);
+                    return None;
+                }
+            }
+
+            self.line.clear();
+            match self.reader.read_line(&mut self.line) {
+                Ok(read) => {
+                    self.position = position + read as u64;
+                    match serde_json::from_str::<CapturedFrame>(&self.line) {
+                        Ok(frame) => return Some(frame.data),
+                        Err(e) => eprintln!("Skipping unreadable capture record: {}", e),
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Error reading capture file: {}", e);
+                    return None;
+                }
+            }
+        }
+        None
+    }
+}
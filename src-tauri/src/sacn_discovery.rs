@@ -0,0 +1,86 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use chrono::Local;
+use sacn::packet::UniverseDiscoveryPacketFramingLayer;
+
+use crate::protocols::SacnDiscoveredSource;
+
+// Sources advertise every 10 seconds; allow one missed advert before dropping them.
+const SOURCE_TIMEOUT: Duration = Duration::from_secs(25);
+
+pub struct AdvertisingSource {
+    name: String,
+    universes: Vec<u16>,
+    // A source's universe list may span several pages that are only complete once all have arrived.
+    pending_pages: BTreeMap<u8, Vec<u16>>,
+    last_seen: String,
+    last_seen_at: Instant,
+}
+
+// Advertising sources keyed by CID.
+pub type DiscoveryTable = Arc<Mutex<HashMap<String, AdvertisingSource>>>;
+
+// Returns true when the complete universe list of a source changed.
+pub fn handle_packet(discovered: &DiscoveryTable, cid: String, packet: &UniverseDiscoveryPacketFramingLayer) -> bool {
+    let mut table = discovered.lock().unwrap();
+    let source = table.entry(cid).or_insert_with(|| AdvertisingSource {
+        name: String::new(),
+        universes: Vec::new(),
+        pending_pages: BTreeMap::new(),
+        last_seen: String::new(),
+        last_seen_at: Instant::now(),
+    });
+    source.name = packet.source_name.trim_end_matches('\0').to_string();
+    source.last_seen = Local::now().format("%H:%M:%S%.3f").to_string();
+    source.last_seen_at = Instant::now();
+
+    let page = &packet.data;
+    if page.page == 0 {
+        source.pending_pages.clear();
+    }
+    source.pending_pages.insert(page.page, page.universes.to_vec());
+    if source.pending_pages.len() <= page.last_page as usize {
+        return false;
+    }
+
+    let mut universes: Vec<u16> = source.pending_pages.values().flatten().copied().collect();
+    universes.sort_unstable();
+    universes.dedup();
+    source.pending_pages.clear();
+
+    let changed = universes != source.universes;
+    source.universes = universes;
+    changed
+}
+
+// Returns true when at least one source stopped advertising.
+pub fn expire_sources(discovered: &DiscoveryTable) -> bool {
+    let mut table = discovered.lock().unwrap();
+    let before = table.len();
+    table.retain(|_, s| s.last_seen_at.elapsed() < SOURCE_TIMEOUT);
+    table.len() != before
+}
+
+pub fn list_sources(discovered: &DiscoveryTable) -> Vec<SacnDiscoveredSource> {
+    let table = discovered.lock().unwrap();
+    let mut sources: Vec<SacnDiscoveredSource> = table.iter()
+        .map(|(cid, s)| SacnDiscoveredSource {
+            cid: cid.clone(),
+            name: s.name.clone(),
+            universes: s.universes.clone(),
+            last_seen: s.last_seen.clone(),
+        })
+        .collect();
+    sources.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.cid.cmp(&b.cid)));
+    sources
+}
+
+// Every universe advertised by any source, for "subscribe to everything".
+pub fn all_universes(discovered: &DiscoveryTable) -> Vec<u16> {
+    let table = discovered.lock().unwrap();
+    let mut universes: Vec<u16> = table.values().flat_map(|s| s.universes.iter().copied()).collect();
+    universes.sort_unstable();
+    universes.dedup();
+    universes
+}
@@ -1,74 +1,227 @@
-use std::{sync::mpsc, time::Duration};
+use std::collections::{HashMap, HashSet};
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
 use chrono::Local;
-use sacn::receive::SacnReceiver;
+use sacn::packet::{AcnRootLayerProtocol, E131RootLayerData, ACN_SDT_MULTICAST_PORT, E131_DISCOVERY_UNIVERSE, E131_NETWORK_DATA_LOSS_TIMEOUT};
+use socket2::{Domain, Protocol, Socket, Type};
 use crate::protocols;
+use crate::sacn_discovery::{self, DiscoveryTable};
 
-pub fn start(tx: mpsc::Sender<protocols::DmxData>, command_rx: mpsc::Receiver<protocols::SacnCommand>) {
+pub struct ActiveSource {
+    info: protocols::SacnSourceInfo,
+    last_seen: String,
+    last_seen_at: Instant,
+}
 
-    let mut receiver: Option<SacnReceiver> = None;
+// Sources currently transmitting, keyed by universe then CID.
+pub type SourceTable = Arc<Mutex<HashMap<u16, HashMap<String, ActiveSource>>>>;
+
+pub fn start(
+    tx: mpsc::Sender<protocols::DmxData>,
+    command_rx: mpsc::Receiver<protocols::SacnCommand>,
+    sources: SourceTable,
+    discovered: DiscoveryTable,
+    discovery_tx: mpsc::Sender<Vec<protocols::SacnDiscoveredSource>>,
+) {
+
+    let mut socket: Option<UdpSocket> = None;
+    let mut interface = Ipv4Addr::UNSPECIFIED;
+    let mut subscribed_universes: HashSet<u16> = HashSet::new();
+    let mut buf = [0u8; 1144];
 
     loop {
         match command_rx.try_recv() {
             Ok(protocols::SacnCommand::Start {ip}) => {
-                let addr = format!("{}:5568", ip);
-                match SacnReceiver::with_ip(
-                    addr.parse().expect("Invalid IP address"),
-                    None,
-                ) {
-                    Ok(r) => {
-                        receiver = Some(r);
-                        println!("sACN Listener started on {}", addr);
+                match ip.parse::<Ipv4Addr>() {
+                    Ok(addr) => {
+                        interface = addr;
+                        match bind_socket() {
+                            Ok(s) => {
+                                if let Err(e) = s.join_multicast_v4(&universe_multicast_addr(E131_DISCOVERY_UNIVERSE), &interface) {
+                                    eprintln!("Failed to join sACN universe discovery: {}", e);
+                                }
+                                socket = Some(s);
+                                eprintln!("sACN Listener started on {}:{}", ip, ACN_SDT_MULTICAST_PORT);
+                            }
+                            Err(e) => {
+                                eprintln!("Failed to start sACN Listener on {}: {}", ip, e);
+                                socket = None;
+                            }
+                        }
                     }
                     Err(e) => {
-                        eprintln!("Failed to start sACN Listener on {}: {}", addr, e);
-                        receiver = None;
+                        eprintln!("Invalid sACN listen IP {}: {}", ip, e);
+                        socket = None;
                     }
                 }
+                subscribed_universes.clear();
+                sources.lock().unwrap().clear();
+                discovered.lock().unwrap().clear();
             }
             Ok(protocols::SacnCommand::Stop) => {
-                receiver = None;
-                println!("sACN Listener stopped");
+                socket = None;
+                subscribed_universes.clear();
+                sources.lock().unwrap().clear();
+                discovered.lock().unwrap().clear();
+                discovery_tx.send(Vec::new()).ok();
+                eprintln!("sACN Listener stopped");
             }
             Ok(protocols::SacnCommand::SubscribeUniverse(u)) => {
-                if let Some(ref mut r) = receiver {
-                    match r.listen_universes(&[u]) {
-                        Ok(_) => println!("Subscribed to universe {}", u),
-                        Err(e) => eprintln!("Failed to subscribe to universe {}: {}", u, e),
+                if let Some(ref s) = socket {
+                    subscribe(s, &interface, &mut subscribed_universes, u);
+                }
+            }
+            Ok(protocols::SacnCommand::SubscribeDiscovered) => {
+                if let Some(ref s) = socket {
+                    for u in sacn_discovery::all_universes(&discovered) {
+                        if !subscribed_universes.contains(&u) {
+                            subscribe(s, &interface, &mut subscribed_universes, u);
+                        }
                     }
                 }
             }
             Ok(protocols::SacnCommand::UnsubscribeUniverse(u)) => {
-                if let Some(ref mut r) = receiver {
-                    match r.mute_universe(u) {
-                        Ok(_) => println!("Unsubscribed from universe {}", u),
+                if let Some(ref s) = socket {
+                    subscribed_universes.remove(&u);
+                    sources.lock().unwrap().remove(&u);
+                    match s.leave_multicast_v4(&universe_multicast_addr(u), &interface) {
+                        Ok(_) => eprintln!("Unsubscribed from universe {}", u),
                         Err(e) => eprintln!("Failed to unsubscribe from universe {}: {}", u, e),
                     }
                 }
             }
+            // No socket and no one left to send Start, so stop.
+            Err(mpsc::TryRecvError::Disconnected) if socket.is_none() => return,
             Err(_) => {}
         }
 
-        if let Some(ref mut r)  = receiver {
-            match r.recv(Some(Duration::from_millis(100))) {
-                Ok(packets) => {
-                    let _timestamp = Local::now().format("%H:%M:%S%.3f").to_string();
-                    
-                    for packet in packets {
-                        tx.send(protocols::DmxData {
-                            universe: packet.universe,
-                            channels: packet.values[1..].to_vec(),
-                        }).unwrap();
+        if let Some(ref s) = socket {
+            match s.recv_from(&mut buf) {
+                Ok((length, _src)) => {
+                    if let Ok(packet) = AcnRootLayerProtocol::parse(&buf[..length]) {
+                        let cid = packet.pdu.cid.to_string();
+                        match packet.pdu.data {
+                            E131RootLayerData::DataPacket(frame) if subscribed_universes.contains(&frame.universe) => {
+                                let info = protocols::SacnSourceInfo {
+                                    cid: cid.clone(),
+                                    name: frame.source_name.trim_end_matches('\0').to_string(),
+                                    priority: frame.priority,
+                                    sequence: frame.sequence_number,
+                                    preview: frame.preview_data,
+                                    terminated: frame.stream_terminated,
+                                };
+
+                                let mut table = sources.lock().unwrap();
+                                let universe_sources = table.entry(frame.universe).or_default();
+                                if info.terminated {
+                                    universe_sources.remove(&cid);
+                                } else {
+                                    universe_sources.insert(cid, ActiveSource {
+                                        info: info.clone(),
+                                        last_seen: Local::now().format("%H:%M:%S%.3f").to_string(),
+                                        last_seen_at: Instant::now(),
+                                    });
+                                }
+                                drop(table);
+
+                                let values = &frame.data.property_values;
+                                tx.send(protocols::DmxData {
+                                    universe: frame.universe,
+                                    channels: values.get(1..).unwrap_or_default().to_vec(),
+                                    source: Some(protocols::DmxSource::Sacn(info)),
+                                    start_code: values.first().copied().unwrap_or(protocols::START_CODE_LEVELS),
+                                }).unwrap();
+                            }
+                            E131RootLayerData::UniverseDiscoveryPacket(discovery) => {
+                                let changed = sacn_discovery::handle_packet(&discovered, cid, &discovery);
+                                if changed {
+                                    discovery_tx.send(sacn_discovery::list_sources(&discovered)).ok();
+                                }
+                            }
+                            _ => {}
+                        }
                     }
                 }
+                Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                    // Timeout reached, no data received
+                }
+                Err(ref e) if e.kind() == std::io::ErrorKind::TimedOut => {
+                    // Timeout reached, no data received
+                }
                 Err(e) => {
-                    let error_str = e.to_string();
-                    if !error_str.contains("timeout") && !error_str.contains("No data") {
-                        eprintln!("Error receiving SACN packet: {}", e);
-                    }
-               }
+                    eprintln!("Error receiving SACN packet: {}", e);
+                }
+            }
+
+            expire_sources(&sources);
+            if sacn_discovery::expire_sources(&discovered) {
+                discovery_tx.send(sacn_discovery::list_sources(&discovered)).ok();
             }
         } else {
             std::thread::sleep(Duration::from_millis(100));
         }
     }
-}
\ No newline at end of file
+}
+
+pub fn active_sources(sources: &SourceTable) -> Vec<protocols::SacnUniverseSources> {
+    let table = sources.lock().unwrap();
+    let mut universes: Vec<protocols::SacnUniverseSources> = table.iter()
+        .filter(|(_, universe_sources)| !universe_sources.is_empty())
+        .map(|(universe, universe_sources)| {
+            let mut list: Vec<protocols::SacnSourceStatus> = universe_sources.values()
+                .map(|s| protocols::SacnSourceStatus {
+                    cid: s.info.cid.clone(),
+                    name: s.info.name.clone(),
+                    priority: s.info.priority,
+                    last_seen: s.last_seen.clone(),
+                })
+                .collect();
+            list.sort_by(|a, b| b.priority.cmp(&a.priority).then_with(|| a.name.cmp(&b.name)));
+            protocols::SacnUniverseSources {
+                universe: *universe,
+                sources: list,
+            }
+        })
+        .collect();
+    universes.sort_by_key(|u| u.universe);
+    universes
+}
+
+// E1.31 considers a source lost once nothing has arrived for the network data loss timeout.
+fn expire_sources(sources: &SourceTable) {
+    let mut table = sources.lock().unwrap();
+    for universe_sources in table.values_mut() {
+        universe_sources.retain(|_, s| s.last_seen_at.elapsed() < E131_NETWORK_DATA_LOSS_TIMEOUT);
+    }
+}
+
+fn subscribe(socket: &UdpSocket, interface: &Ipv4Addr, subscribed_universes: &mut HashSet<u16>, universe: u16) {
+    match socket.join_multicast_v4(&universe_multicast_addr(universe), interface) {
+        Ok(_) => {
+            subscribed_universes.insert(universe);
+            eprintln!("Subscribed to universe {}", universe);
+        }
+        Err(e) => eprintln!("Failed to subscribe to universe {}: {}", universe, e),
+    }
+}
+
+fn universe_multicast_addr(universe: u16) -> Ipv4Addr {
+    Ipv4Addr::new(239, 255, (universe >> 8) as u8, (universe & 0xFF) as u8)
+}
+
+// Multicast traffic is only delivered to sockets bound to the wildcard address, the
+// interface is chosen when joining each universe's group instead.
+fn bind_socket() -> std::io::Result<UdpSocket> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_reuse_address(true)?;
+    #[cfg(not(target_os = "windows"))]
+    socket.set_reuse_port(true)?;
+
+    let addr = SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), ACN_SDT_MULTICAST_PORT);
+    socket.bind(&addr.into())?;
+
+    let socket: UdpSocket = socket.into();
+    socket.set_read_timeout(Some(Duration::from_millis(100)))?;
+    Ok(socket)
+}
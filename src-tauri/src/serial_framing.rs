@@ -0,0 +1,261 @@
+use std::time::{Duration, Instant};
+
+use crate::protocols::SerialFraming;
+
+// Anything longer without a frame boundary is passed on as is, so a wrong framing
+// choice can't make the buffer grow without bound. Length-prefixed frames that
+// claim to be longer are skipped instead, so the next header is still found.
+const MAX_FRAME_LENGTH: usize = 65536;
+
+const SLIP_END: u8 = 0xC0;
+const SLIP_ESC: u8 = 0xDB;
+const SLIP_ESC_END: u8 = 0xDC;
+const SLIP_ESC_ESC: u8 = 0xDD;
+
+pub fn validate(framing: &SerialFraming) -> Result<(), String> {
+    match framing {
+        SerialFraming::Delimiter { delimiter, .. } if delimiter.is_empty() => Err("Delimiter must not be empty".to_string()),
+        SerialFraming::Idle { timeout_ms: 0 } => Err("Idle timeout must be longer than 0 ms".to_string()),
+        SerialFraming::Fixed { length } if *length == 0 || *length > MAX_FRAME_LENGTH => {
+            Err(format!("Frame length must be between 1 and {}", MAX_FRAME_LENGTH))
+        }
+        SerialFraming::LengthPrefix { width, .. } if !matches!(width, 1 | 2 | 4) => {
+            Err("Length prefix must be 1, 2 or 4 bytes wide".to_string())
+        }
+        _ => Ok(()),
+    }
+}
+
+pub struct FrameDecoder {
+    framing: SerialFraming,
+    buffer: Vec<u8>,
+    last_byte_at: Instant,
+    slip_escaped: bool,
+    // Bytes still to discard of an oversize length-prefixed frame.
+    skip: usize,
+}
+
+impl FrameDecoder {
+    pub fn new(framing: SerialFraming) -> FrameDecoder {
+        FrameDecoder {
+            framing,
+            buffer: Vec::new(),
+            last_byte_at: Instant::now(),
+            slip_escaped: false,
+            skip: 0,
+        }
+    }
+
+    // Drops a partial frame, e.g. when the port is reopened.
+    pub fn reset(&mut self) {
+        self.buffer.clear();
+        self.slip_escaped = false;
+        self.skip = 0;
+    }
+
+    // Returns every frame completed by `data`.
+    pub fn push(&mut self, data: &[u8]) -> Vec<Vec<u8>> {
+        self.last_byte_at = Instant::now();
+        let mut frames = Vec::new();
+        match self.framing {
+            SerialFraming::Raw => frames.push(data.to_vec()),
+            SerialFraming::Delimiter { ref delimiter, keep_delimiter } => {
+                for &b in data {
+                    self.buffer.push(b);
+                    if self.buffer.ends_with(delimiter) {
+                        let mut frame = std::mem::take(&mut self.buffer);
+                        if !keep_delimiter {
+                            frame.truncate(frame.len() - delimiter.len());
+                        }
+                        frames.push(frame);
+                    }
+                }
+            }
+            SerialFraming::Idle { .. } => self.buffer.extend_from_slice(data),
+            SerialFraming::Fixed { length } => {
+                self.buffer.extend_from_slice(data);
+                while self.buffer.len() >= length {
+                    frames.push(self.buffer.drain(..length).collect());
+                }
+            }
+            SerialFraming::LengthPrefix { width, little_endian, includes_header } => {
+                self.buffer.extend_from_slice(data);
+                let width = width as usize;
+                loop {
+                    if self.skip > 0 {
+                        let skipped = self.skip.min(self.buffer.len());
+                        self.buffer.drain(..skipped);
+                        self.skip -= skipped;
+                        if self.skip > 0 {
+                            break;
+                        }
+                    }
+                    if self.buffer.len() < width {
+                        break;
+                    }
+                    let header = &self.buffer[..width];
+                    let value = match (width, little_endian) {
+                        (1, _) => header[0] as usize,
+                        (2, false) => u16::from_be_bytes([header[0], header[1]]) as usize,
+                        (2, true) => u16::from_le_bytes([header[0], header[1]]) as usize,
+                        (_, false) => u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize,
+                        (_, true) => u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as usize,
+                    };
+                    // A header smaller than itself can't be satisfied; treat it as an empty frame.
+                    let length = if includes_header { value.max(width) } else { value.saturating_add(width) };
+                    if length > MAX_FRAME_LENGTH {
+                        eprintln!("Skipping length-prefixed frame of {} bytes, longer than {}", length, MAX_FRAME_LENGTH);
+                        self.skip = length;
+                        continue;
+                    }
+                    if self.buffer.len() < length {
+                        break;
+                    }
+                    frames.push(self.buffer.drain(..length).collect());
+                }
+            }
+            SerialFraming::Slip => {
+                for &b in data {
+                    match (self.slip_escaped, b) {
+                        (false, SLIP_END) => {
+                            if !self.buffer.is_empty() {
+                                frames.push(std::mem::take(&mut self.buffer));
+                            }
+                        }
+                        (false, SLIP_ESC) => self.slip_escaped = true,
+                        (false, b) => self.buffer.push(b),
+                        (true, b) => {
+                            self.slip_escaped = false;
+                            self.buffer.push(match b {
+                                SLIP_ESC_END => SLIP_END,
+                                SLIP_ESC_ESC => SLIP_ESC,
+                                // Protocol violation; RFC 1055 suggests keeping the byte.
+                                other => other,
+                            });
+                        }
+                    }
+                }
+            }
+            SerialFraming::Cobs => {
+                for &b in data {
+                    if b != 0x00 {
+                        self.buffer.push(b);
+                        continue;
+                    }
+                    let encoded = std::mem::take(&mut self.buffer);
+                    if encoded.is_empty() {
+                        continue;
+                    }
+                    match cobs_decode(&encoded) {
+                        Some(frame) => frames.push(frame),
+                        None => eprintln!("Dropping malformed COBS frame of {} bytes", encoded.len()),
+                    }
+                }
+            }
+        }
+
+        if self.buffer.len() > MAX_FRAME_LENGTH {
+            frames.push(std::mem::take(&mut self.buffer));
+        }
+        frames
+    }
+
+    // When the idle timeout will expire, so the reader can wake up in time.
+    pub fn idle_deadline(&self) -> Option<Instant> {
+        match self.framing {
+            SerialFraming::Idle { timeout_ms } if !self.buffer.is_empty() => {
+                Some(self.last_byte_at + Duration::from_millis(timeout_ms))
+            }
+            _ => None,
+        }
+    }
+
+    // Returns the buffered frame once the line has been idle long enough.
+    pub fn poll_idle(&mut self) -> Option<Vec<u8>> {
+        match self.idle_deadline() {
+            Some(deadline) if Instant::now() >= deadline => Some(std::mem::take(&mut self.buffer)),
+            _ => None,
+        }
+    }
+}
+
+fn cobs_decode(encoded: &[u8]) -> Option<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(encoded.len());
+    let mut i = 0;
+    while i < encoded.len() {
+        let code = encoded[i] as usize;
+        let end = i + code;
+        if code == 0 || end > encoded.len() {
+            return None;
+        }
+        decoded.extend_from_slice(&encoded[i + 1..end]);
+        i = end;
+        if code < 0xFF && i < encoded.len() {
+            decoded.push(0x00);
+        }
+    }
+    Some(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Case = (SerialFraming, &'static [&'static [u8]], &'static [&'static [u8]]);
+
+    fn decode(framing: SerialFraming, chunks: &[&[u8]]) -> Vec<Vec<u8>> {
+        let mut decoder = FrameDecoder::new(framing);
+        chunks.iter().flat_map(|chunk| decoder.push(chunk)).collect()
+    }
+
+    #[test]
+    fn frames() {
+        let lf = || SerialFraming::Delimiter { delimiter: b"\r\n".to_vec(), keep_delimiter: false };
+        let prefix = |width, little_endian, includes_header| SerialFraming::LengthPrefix { width, little_endian, includes_header };
+        let cases: Vec<Case> = vec![
+            (SerialFraming::Raw, &[b"ab", b"c"], &[b"ab", b"c"]),
+            (lf(), &[b"GO\r", b"\nST", b"OP\r\n"], &[b"GO", b"STOP"]),
+            (SerialFraming::Delimiter { delimiter: b"\n".to_vec(), keep_delimiter: true }, &[b"a\nb"], &[b"a\n"]),
+            (SerialFraming::Fixed { length: 3 }, &[b"abcd", b"ef"], &[b"abc", b"def"]),
+            (prefix(1, false, false), &[&[2, b'a'], &[b'b', 0, 1]], &[&[2, b'a', b'b'], &[0]]),
+            (prefix(2, false, false), &[&[0, 1, b'a']], &[&[0, 1, b'a']]),
+            (prefix(2, true, false), &[&[1, 0, b'a']], &[&[1, 0, b'a']]),
+            (prefix(4, false, true), &[&[0, 0, 0, 5, b'a', 0, 0, 0, 1]], &[&[0, 0, 0, 5, b'a'], &[0, 0, 0, 1]]),
+            (SerialFraming::Slip, &[&[0xC0, b'a', 0xDB, 0xDC, 0xDB], &[0xDD, 0xC0, 0xC0]], &[&[b'a', 0xC0, 0xDB]]),
+            (SerialFraming::Cobs, &[&[0x03, 0x11, 0x22, 0x02], &[0x33, 0x00, 0x00]], &[&[0x11, 0x22, 0x00, 0x33]]),
+            (SerialFraming::Cobs, &[&[0x05, 0x11, 0x00, 0x01, 0x00]], &[&[]]),
+        ];
+        for (i, (framing, chunks, expected)) in cases.into_iter().enumerate() {
+            assert_eq!(decode(framing, chunks), expected, "case {}", i);
+        }
+    }
+
+    #[test]
+    fn oversize_length_prefix_is_skipped() {
+        let mut data = vec![0x00, 0x01, 0x00, 0x01];
+        data.extend(vec![0xAA; MAX_FRAME_LENGTH + 1 - 4]);
+        data.extend([0x00, 0x00, 0x00, 0x05, b'a']);
+        let framing = SerialFraming::LengthPrefix { width: 4, little_endian: false, includes_header: true };
+        assert_eq!(decode(framing, &[&data[..1000], &data[1000..]]), [vec![0, 0, 0, 5, b'a']]);
+    }
+
+    #[test]
+    fn cobs() {
+        let cases: &[(&[u8], Option<&[u8]>)] = &[
+            (&[0x01], Some(&[])),
+            (&[0x01, 0x01], Some(&[0x00])),
+            (&[0x03, 0x11, 0x22], Some(&[0x11, 0x22])),
+            (&[0x02, 0x11, 0x01, 0x01], Some(&[0x11, 0x00, 0x00])),
+            (&[0x04, 0x11], None),
+        ];
+        for &(encoded, expected) in cases {
+            assert_eq!(cobs_decode(encoded).as_deref(), expected, "{:02X?}", encoded);
+        }
+        let mut long = vec![0xFF];
+        long.extend(1..=254u8);
+        long.extend([0x02, 0x07]);
+        let mut expected: Vec<u8> = (1..=254).collect();
+        expected.push(0x07);
+        assert_eq!(cobs_decode(&long), Some(expected));
+    }
+}
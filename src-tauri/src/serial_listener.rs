@@ -1,68 +1,138 @@
-use std::sync::mpsc::{Receiver, Sender};
-use std::time::Duration;
-use std::io::Read;
+use std::sync::mpsc::{Receiver, Sender, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use std::io::{Read, Write};
 use chrono::Local;
-use serialport;
 
-use crate::protocols::{SerialData, SerialCommand, SerialPortInfo};
+use crate::midi_parser::{self, MidiParser};
+use crate::protocols::{MidiData, SerialCommand, SerialData, SerialFlowControl, SerialFraming, SerialLineConfig, SerialModemLines, SerialParity, SerialPortInfo};
+use crate::serial_framing::FrameDecoder;
 
-pub fn start(tx: Sender<SerialData>, command_rx: Receiver<SerialCommand>) {
+const READ_TIMEOUT: Duration = Duration::from_millis(100);
+
+// Name of the port currently open, so writes can be refused before they're queued.
+pub type OpenPort = Arc<Mutex<Option<String>>>;
+
+struct PendingWrite {
+    bytes: Vec<u8>,
+    remaining: u32,
+    interval: Duration,
+    next: Instant,
+}
+
+pub fn start(tx: Sender<SerialData>, command_rx: Receiver<SerialCommand>, open_port_name: OpenPort, modem_tx: Sender<SerialModemLines>, midi_tx: Sender<MidiData>) {
     let mut port: Option<Box<dyn serialport::SerialPort>> = None;
+    let mut midi_source = String::new();
+    let mut midi: Option<MidiParser> = None;
     let mut buf: [u8; 256] = [0u8; 256];
+    let mut pending_write: Option<PendingWrite> = None;
+    let mut decoder = FrameDecoder::new(SerialFraming::Raw);
+    let mut modem_lines: Option<SerialModemLines> = None;
+    // Cleared when the port can't report modem lines (e.g. virtual or USB CDC ports without them).
+    let mut modem_supported = true;
 
     loop {
         // Check for commands
         match command_rx.try_recv() {
-            Ok(SerialCommand::Start { port: port_name, baud_rate }) => {
-                match serialport::new(&port_name, baud_rate)
-                    .timeout(Duration::from_millis(100))
-                    .open()
-                {
+            Ok(SerialCommand::Start { port: port_name, baud_rate, config }) => {
+                match open_port(&port_name, baud_rate, &config) {
                     Ok(p) => {
                         port = Some(p);
-                        println!("Serial port opened: {} @ {} baud", port_name, baud_rate);
+                        *open_port_name.lock().unwrap() = Some(port_name.clone());
+                        decoder.reset();
+                        midi_source = format!("serial:{}", port_name);
+                        if midi.is_some() {
+                            midi = Some(MidiParser::default());
+                        }
+                        modem_lines = None;
+                        modem_supported = true;
+                        eprintln!("Serial port opened: {} @ {} baud {}", port_name, baud_rate, line_format(&config));
                     }
                     Err(e) => {
                         eprintln!("Failed to open serial port {}: {}", port_name, e);
                         port = None;
+                        *open_port_name.lock().unwrap() = None;
                     }
                 }
             }
             Ok(SerialCommand::Stop) => {
                 port = None;
-                println!("Serial port closed");
+                *open_port_name.lock().unwrap() = None;
+                pending_write = None;
+                eprintln!("Serial port closed");
+            }
+            Ok(SerialCommand::SetFraming(framing)) => {
+                decoder = FrameDecoder::new(framing);
+            }
+            Ok(SerialCommand::SetMidiDecoding(enabled)) => {
+                midi = enabled.then(MidiParser::default);
+            }
+            Ok(SerialCommand::SetDtr(level)) => {
+                if let Some(ref mut p) = port {
+                    if let Err(e) = p.write_data_terminal_ready(level) {
+                        eprintln!("Failed to set DTR: {}", e);
+                    }
+                }
+            }
+            Ok(SerialCommand::SetRts(level)) => {
+                if let Some(ref mut p) = port {
+                    if let Err(e) = p.write_request_to_send(level) {
+                        eprintln!("Failed to set RTS: {}", e);
+                    }
+                }
             }
+            Ok(SerialCommand::Write { bytes, count, interval }) => {
+                if port.is_some() {
+                    // A new write replaces any repeat still in progress.
+                    pending_write = Some(PendingWrite {
+                        bytes,
+                        remaining: count,
+                        interval,
+                        next: Instant::now(),
+                    });
+                } else {
+                    eprintln!("Serial write ignored, no port is open");
+                }
+            }
+            // The port failed or was closed and nothing can reopen it.
+            Err(TryRecvError::Disconnected) if port.is_none() => return,
             Err(_) => {}
         }
 
+        if let (Some(ref mut p), Some(ref mut write)) = (&mut port, &mut pending_write) {
+            if Instant::now() >= write.next {
+                if let Err(e) = p.write_all(&write.bytes) {
+                    eprintln!("Serial write error: {}", e);
+                }
+                write.remaining = write.remaining.saturating_sub(1);
+                write.next += write.interval;
+            }
+        }
+        if pending_write.as_ref().is_some_and(|w| w.remaining == 0) {
+            pending_write = None;
+        }
+
         // Read from port if open
         if let Some(ref mut p) = port {
+            // Wake up in time for the next repeated write or idle frame boundary.
+            let wake_at = [pending_write.as_ref().map(|w| w.next), decoder.idle_deadline()]
+                .into_iter()
+                .flatten()
+                .min();
+            let timeout = wake_at
+                .map(|t| t.saturating_duration_since(Instant::now()).min(READ_TIMEOUT))
+                .unwrap_or(READ_TIMEOUT);
+            p.set_timeout(timeout.max(Duration::from_millis(1))).ok();
             match p.read(&mut buf) {
                 Ok(bytes_read) if bytes_read > 0 => {
-                    let data = &buf[..bytes_read];
-                    let timestamp = Local::now().format("%H:%M:%S%.3f").to_string();
-                    
-                    let hex = data.iter()
-                        .map(|b| format!("{:02X}", b))
-                        .collect::<Vec<_>>()
-                        .join(" ");
-                    
-                    let ascii = data.iter()
-                        .map(|&b| {
-                            if b >= 0x20 && b <= 0x7E {
-                                b as char
-                            } else {
-                                '.'
-                            }
-                        })
-                        .collect::<String>();
-
-                    tx.send(SerialData {
-                        timestamp,
-                        bytes: data.to_vec(),
-                        hex,
-                        ascii,
-                    }).ok();
+                    for frame in decoder.push(&buf[..bytes_read]) {
+                        tx.send(serial_data(&frame)).ok();
+                    }
+                    if let Some(ref mut parser) = midi {
+                        for (message, raw) in parser.parse(&buf[..bytes_read]) {
+                            midi_tx.send(midi_parser::midi_data(&midi_source, message, raw)).ok();
+                        }
+                    }
                 }
                 Ok(_) => {} // No data
                 Err(ref e) if e.kind() == std::io::ErrorKind::TimedOut => {}
@@ -71,12 +141,115 @@ pub fn start(tx: Sender<SerialData>, command_rx: Receiver<SerialCommand>) {
                     eprintln!("Serial read error: {}", e);
                 }
             }
+
+            if let Some(frame) = decoder.poll_idle() {
+                tx.send(serial_data(&frame)).ok();
+            }
+
+            if modem_supported {
+                match read_modem_lines(p.as_mut()) {
+                    Ok(lines) => {
+                        if modem_lines.as_ref() != Some(&lines) {
+                            modem_tx.send(lines.clone()).ok();
+                            modem_lines = Some(lines);
+                        }
+                    }
+                    Err(e) => {
+                        eprintln!("Modem lines unavailable: {}", e);
+                        modem_supported = false;
+                    }
+                }
+            }
         } else {
             std::thread::sleep(Duration::from_millis(100));
         }
     }
 }
 
+fn serial_data(bytes: &[u8]) -> SerialData {
+    let hex = bytes.iter()
+        .map(|b| format!("{:02X}", b))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let ascii = bytes.iter()
+        .map(|&b| {
+            if (0x20..=0x7E).contains(&b) {
+                b as char
+            } else {
+                '.'
+            }
+        })
+        .collect::<String>();
+
+    SerialData {
+        timestamp: Local::now().format("%H:%M:%S%.3f").to_string(),
+        bytes: bytes.to_vec(),
+        hex,
+        ascii,
+    }
+}
+
+fn open_port(name: &str, baud_rate: u32, config: &SerialLineConfig) -> Result<Box<dyn serialport::SerialPort>, String> {
+    let data_bits = match config.data_bits {
+        5 => serialport::DataBits::Five,
+        6 => serialport::DataBits::Six,
+        7 => serialport::DataBits::Seven,
+        8 => serialport::DataBits::Eight,
+        other => return Err(format!("Unsupported data bits: {}", other)),
+    };
+    let stop_bits = match config.stop_bits {
+        1 => serialport::StopBits::One,
+        2 => serialport::StopBits::Two,
+        other => return Err(format!("Unsupported stop bits: {}", other)),
+    };
+    let parity = match config.parity {
+        SerialParity::None => serialport::Parity::None,
+        SerialParity::Odd => serialport::Parity::Odd,
+        SerialParity::Even => serialport::Parity::Even,
+    };
+    let flow_control = match config.flow_control {
+        SerialFlowControl::None => serialport::FlowControl::None,
+        SerialFlowControl::Software => serialport::FlowControl::Software,
+        SerialFlowControl::Hardware => serialport::FlowControl::Hardware,
+    };
+
+    let mut port = serialport::new(name, baud_rate)
+        .data_bits(data_bits)
+        .parity(parity)
+        .stop_bits(stop_bits)
+        .flow_control(flow_control)
+        .timeout(READ_TIMEOUT)
+        .open()
+        .map_err(|e| e.to_string())?;
+    if let Some(dtr) = config.dtr {
+        port.write_data_terminal_ready(dtr).map_err(|e| e.to_string())?;
+    }
+    if let Some(rts) = config.rts {
+        port.write_request_to_send(rts).map_err(|e| e.to_string())?;
+    }
+    Ok(port)
+}
+
+fn read_modem_lines(port: &mut dyn serialport::SerialPort) -> serialport::Result<SerialModemLines> {
+    Ok(SerialModemLines {
+        cts: port.read_clear_to_send()?,
+        dsr: port.read_data_set_ready()?,
+        ri: port.read_ring_indicator()?,
+        cd: port.read_carrier_detect()?,
+    })
+}
+
+// The usual shorthand, e.g. "8N1" or "7E2".
+fn line_format(config: &SerialLineConfig) -> String {
+    let parity = match config.parity {
+        SerialParity::None => 'N',
+        SerialParity::Odd => 'O',
+        SerialParity::Even => 'E',
+    };
+    format!("{}{}{}", config.data_bits, parity, config.stop_bits)
+}
+
 pub fn list_ports() -> Vec<SerialPortInfo> {
     serialport::available_ports()
         .unwrap_or_default()
@@ -0,0 +1,125 @@
+use crate::protocols::{LineEnding, SerialPayloadFormat};
+
+// Turns user input into the bytes to write. For `File`, `data` is the path to send.
+pub fn parse(data: &str, format: SerialPayloadFormat, line_ending: LineEnding) -> Result<Vec<u8>, String> {
+    let mut bytes = match format {
+        SerialPayloadFormat::Hex => parse_hex(data)?,
+        SerialPayloadFormat::Ascii => parse_escaped(data)?,
+        SerialPayloadFormat::File => std::fs::read(data).map_err(|e| format!("Failed to read {}: {}", data, e))?,
+    };
+    match line_ending {
+        LineEnding::None => {}
+        LineEnding::Cr => bytes.push(b'\r'),
+        LineEnding::Lf => bytes.push(b'\n'),
+        LineEnding::Crlf => bytes.extend_from_slice(b"\r\n"),
+    }
+    if bytes.is_empty() {
+        return Err("Nothing to send".to_string());
+    }
+    Ok(bytes)
+}
+
+// Accepts "0D 0A", "0x0D,0x0A" and "0d0a" alike.
+fn parse_hex(data: &str) -> Result<Vec<u8>, String> {
+    let digits: String = data
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .map(|token| token.trim_start_matches("0x").trim_start_matches("0X"))
+        .collect();
+    if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("Hex input contains non-hex characters: {}", data));
+    }
+    if !digits.len().is_multiple_of(2) {
+        return Err(format!("Hex input has an odd number of digits: {}", data));
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).map_err(|_| format!("Invalid hex byte {}", &digits[i..i + 2])))
+        .collect()
+}
+
+// Supports \r, \n, \t, \0, \\ and \xHH.
+fn parse_escaped(data: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::with_capacity(data.len());
+    let mut chars = data.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0u8; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+        match chars.next() {
+            Some('r') => bytes.push(b'\r'),
+            Some('n') => bytes.push(b'\n'),
+            Some('t') => bytes.push(b'\t'),
+            Some('0') => bytes.push(0),
+            Some('\\') => bytes.push(b'\\'),
+            Some('x') => {
+                let hex: String = chars.by_ref().take(2).collect();
+                // from_str_radix alone would take "+F" or a single digit.
+                if hex.len() != 2 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return Err(format!("Invalid escape \\x{}, expected two hex digits", hex));
+                }
+                let byte = u8::from_str_radix(&hex, 16).map_err(|_| format!("Invalid escape \\x{}", hex))?;
+                bytes.push(byte);
+            }
+            Some(other) => return Err(format!("Unknown escape \\{}", other)),
+            None => return Err("Input ends with a lone backslash".to_string()),
+        }
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex() {
+        let cases: &[(&str, Option<&[u8]>)] = &[
+            ("0D 0A", Some(b"\r\n")),
+            ("0x0D,0x0A", Some(b"\r\n")),
+            ("0d0a", Some(b"\r\n")),
+            ("0X41 42", Some(b"AB")),
+            ("", Some(b"")),
+            ("0D0", None),
+            ("0G", None),
+            ("+F", None),
+        ];
+        for &(input, expected) in cases {
+            assert_eq!(parse_hex(input).ok().as_deref(), expected, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn escaped() {
+        let cases: &[(&str, Option<&[u8]>)] = &[
+            ("GO\\r\\n", Some(b"GO\r\n")),
+            ("a\\tb\\0\\\\", Some(b"a\tb\0\\")),
+            ("\\x7F\\xff", Some(&[0x7F, 0xFF])),
+            ("\u{e9}", Some("\u{e9}".as_bytes())),
+            ("\\x", None),
+            ("\\x7", None),
+            ("\\x+F", None),
+            ("\\q", None),
+            ("end\\", None),
+        ];
+        for &(input, expected) in cases {
+            assert_eq!(parse_escaped(input).ok().as_deref(), expected, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn line_endings() {
+        let cases: &[(&str, LineEnding, Option<&[u8]>)] = &[
+            ("A", LineEnding::None, Some(b"A")),
+            ("A", LineEnding::Cr, Some(b"A\r")),
+            ("A", LineEnding::Lf, Some(b"A\n")),
+            ("A", LineEnding::Crlf, Some(b"A\r\n")),
+            ("", LineEnding::Lf, Some(b"\n")),
+            ("", LineEnding::None, None),
+        ];
+        for &(input, line_ending, expected) in cases {
+            assert_eq!(parse(input, SerialPayloadFormat::Ascii, line_ending).ok().as_deref(), expected, "{:?}", input);
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use chrono::Local;
+
+use crate::protocols::{DmxData, DmxSource, OscData, StreamStats};
+
+pub const DEFAULT_WINDOW: Duration = Duration::from_secs(5);
+
+// E1.31 6.7.2: a sequence number up to 20 behind the last one is a late packet,
+// anything further back is taken as the source having restarted. Art-Net has no
+// equivalent rule so the same window is used for both.
+const REORDER_WINDOW: i16 = 20;
+
+#[derive(Hash, PartialEq, Eq, Clone)]
+struct StreamKey {
+    protocol: &'static str,
+    universe: Option<u16>,
+    source: String,
+}
+
+enum Sequence {
+    None,
+    Sacn(u8),
+    // Art-Net uses 1..=255 and reserves 0 for "sequencing disabled".
+    Artnet(u8),
+}
+
+struct Arrival {
+    at: Instant,
+    interval: Option<Duration>,
+    lost: u64,
+    out_of_order: bool,
+}
+
+struct Stream {
+    source_name: String,
+    arrivals: VecDeque<Arrival>,
+    last_arrival: Option<Instant>,
+    last_sequence: Option<u8>,
+    last_seen: String,
+    total_frames: u64,
+    total_lost: u64,
+    total_out_of_order: u64,
+}
+
+impl Stream {
+    fn new() -> Stream {
+        Stream {
+            source_name: String::new(),
+            arrivals: VecDeque::new(),
+            last_arrival: None,
+            last_sequence: None,
+            last_seen: String::new(),
+            total_frames: 0,
+            total_lost: 0,
+            total_out_of_order: 0,
+        }
+    }
+
+    // Returns how many packets were skipped and whether this one arrived late.
+    fn check_sequence(&mut self, sequence: Sequence) -> (u64, bool) {
+        let (current, modulus) = match sequence {
+            Sequence::None => return (0, false),
+            Sequence::Artnet(0) => return (0, false),
+            Sequence::Artnet(s) => (s, 255i16),
+            Sequence::Sacn(s) => (s, 256i16),
+        };
+        let Some(last) = self.last_sequence else {
+            self.last_sequence = Some(current);
+            return (0, false);
+        };
+
+        let mut diff = (current as i16 - last as i16).rem_euclid(modulus);
+        if diff > modulus / 2 {
+            diff -= modulus;
+        }
+
+        if diff <= 0 && diff > -REORDER_WINDOW {
+            (0, true)
+        } else {
+            self.last_sequence = Some(current);
+            if diff > 1 { ((diff - 1) as u64, false) } else { (0, false) }
+        }
+    }
+
+    fn record(&mut self, now: Instant, sequence: Sequence, window: Duration) {
+        let (lost, out_of_order) = self.check_sequence(sequence);
+        self.arrivals.push_back(Arrival {
+            at: now,
+            interval: self.last_arrival.map(|t| now.duration_since(t)),
+            lost,
+            out_of_order,
+        });
+        self.last_arrival = Some(now);
+        self.last_seen = Local::now().format("%H:%M:%S%.3f").to_string();
+        self.total_frames += 1;
+        self.total_lost += lost;
+        if out_of_order {
+            self.total_out_of_order += 1;
+        }
+        self.trim(now, window);
+    }
+
+    fn trim(&mut self, now: Instant, window: Duration) {
+        while self.arrivals.front().is_some_and(|a| now.duration_since(a.at) > window) {
+            self.arrivals.pop_front();
+        }
+    }
+}
+
+pub struct Stats {
+    streams: Mutex<HashMap<StreamKey, Stream>>,
+    window: Mutex<Duration>,
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Stats {
+            streams: Mutex::new(HashMap::new()),
+            window: Mutex::new(DEFAULT_WINDOW),
+        }
+    }
+}
+
+impl Stats {
+    pub fn record_dmx(&self, protocol: &'static str, dmx: &DmxData) {
+        let (source, source_name, sequence) = match dmx.source {
+            Some(DmxSource::Sacn(ref info)) => (info.cid.clone(), info.name.clone(), Sequence::Sacn(info.sequence)),
+            Some(DmxSource::Artnet(ref info)) => (info.sender.clone(), info.sender.clone(), Sequence::Artnet(info.sequence)),
+            Some(DmxSource::Enttec(ref info)) => (info.port.clone(), info.serial_number.clone(), Sequence::None),
+            None => (String::new(), String::new(), Sequence::None),
+        };
+        let key = StreamKey {
+            protocol,
+            universe: Some(dmx.universe),
+            source,
+        };
+        self.record(key, source_name, sequence);
+    }
+
+    pub fn record_osc(&self, osc: &OscData) {
+        let key = StreamKey {
+            protocol: "osc",
+            universe: None,
+            source: osc.sender.clone(),
+        };
+        self.record(key, osc.sender.clone(), Sequence::None);
+    }
+
+    fn record(&self, key: StreamKey, source_name: String, sequence: Sequence) {
+        let window = *self.window.lock().unwrap();
+        let mut streams = self.streams.lock().unwrap();
+        let stream = streams.entry(key).or_insert_with(Stream::new);
+        stream.source_name = source_name;
+        stream.record(Instant::now(), sequence, window);
+    }
+
+    pub fn set_window(&self, window: Duration) {
+        *self.window.lock().unwrap() = window;
+    }
+
+    pub fn reset(&self) {
+        self.streams.lock().unwrap().clear();
+    }
+
+    pub fn snapshot(&self) -> Vec<StreamStats> {
+        let window = *self.window.lock().unwrap();
+        let now = Instant::now();
+        let mut streams = self.streams.lock().unwrap();
+
+        let mut snapshot: Vec<StreamStats> = streams.iter_mut()
+            .map(|(key, stream)| {
+                stream.trim(now, window);
+
+                let intervals: Vec<f64> = stream.arrivals.iter()
+                    .filter_map(|a| a.interval)
+                    .map(|d| d.as_secs_f64() * 1000.0)
+                    .collect();
+                let count = intervals.len() as f64;
+                let (min, max, avg, stddev) = if intervals.is_empty() {
+                    (0.0, 0.0, 0.0, 0.0)
+                } else {
+                    let avg = intervals.iter().sum::<f64>() / count;
+                    let variance = intervals.iter().map(|i| (i - avg).powi(2)).sum::<f64>() / count;
+                    (
+                        intervals.iter().copied().fold(f64::INFINITY, f64::min),
+                        intervals.iter().copied().fold(0.0, f64::max),
+                        avg,
+                        variance.sqrt(),
+                    )
+                };
+
+                StreamStats {
+                    protocol: key.protocol.to_string(),
+                    universe: key.universe,
+                    source: key.source.clone(),
+                    source_name: stream.source_name.clone(),
+                    fps: stream.arrivals.len() as f64 / window.as_secs_f64(),
+                    interval_min_ms: min,
+                    interval_avg_ms: avg,
+                    interval_max_ms: max,
+                    interval_stddev_ms: stddev,
+                    window_frames: stream.arrivals.len() as u64,
+                    window_lost: stream.arrivals.iter().map(|a| a.lost).sum(),
+                    window_out_of_order: stream.arrivals.iter().filter(|a| a.out_of_order).count() as u64,
+                    total_frames: stream.total_frames,
+                    total_lost: stream.total_lost,
+                    total_out_of_order: stream.total_out_of_order,
+                    last_seen: stream.last_seen.clone(),
+                }
+            })
+            .collect();
+
+        snapshot.sort_by(|a, b| {
+            a.protocol.cmp(&b.protocol)
+                .then_with(|| a.universe.cmp(&b.universe))
+                .then_with(|| a.source.cmp(&b.source))
+        });
+        snapshot
+    }
+}
@@ -0,0 +1,213 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use chrono::Local;
+
+use crate::protocols::{Timecode, TimecodeData, TimecodeRate};
+
+// A gap this many frames long counts as a dropout and restarts drift measurement.
+const DROPOUT_FRAMES: f64 = 5.0;
+// Frames that went missing without a dropout, e.g. lost packets, up to this many
+// count as skipped; anything further is a jump.
+const MAX_SKIP: i64 = 10;
+// How often to call `TimecodeTracker::expire`, well inside the dropout gap.
+pub const EXPIRE_INTERVAL: Duration = Duration::from_millis(100);
+
+pub fn frames_per_second(rate: TimecodeRate) -> u8 {
+    match rate {
+        TimecodeRate::Film => 24,
+        TimecodeRate::Ebu => 25,
+        TimecodeRate::DropFrame | TimecodeRate::Smpte => 30,
+    }
+}
+
+// The two-bit rate code shared by MTC and ArtTimeCode.
+pub fn rate_from_code(code: u8) -> TimecodeRate {
+    match code & 0x03 {
+        0 => TimecodeRate::Film,
+        1 => TimecodeRate::Ebu,
+        2 => TimecodeRate::DropFrame,
+        _ => TimecodeRate::Smpte,
+    }
+}
+
+// Steps forward one frame, skipping the frame numbers drop frame leaves out and
+// wrapping at 24 hours. Fields from the wire can be out of range, so each one
+// carries as soon as it reaches its limit rather than only on the exact value.
+pub fn next_frame(tc: Timecode) -> Timecode {
+    let mut next = tc;
+    next.frames = next.frames.saturating_add(1);
+    if next.frames < frames_per_second(tc.rate) {
+        return next;
+    }
+    next.frames = 0;
+    next.seconds = next.seconds.saturating_add(1);
+    if next.seconds >= 60 {
+        next.seconds = 0;
+        next.minutes = next.minutes.saturating_add(1);
+        if next.minutes >= 60 {
+            next.minutes = 0;
+            next.hours = (next.hours % 24 + 1) % 24;
+        }
+        // Drop frame skips frames 0 and 1 at the start of every minute except each tenth.
+        if tc.rate == TimecodeRate::DropFrame && !next.minutes.is_multiple_of(10) {
+            next.frames = 2;
+        }
+    }
+    next
+}
+
+pub fn frame_duration(rate: TimecodeRate) -> Duration {
+    match rate {
+        TimecodeRate::DropFrame => Duration::from_secs_f64(1001.0 / 30000.0),
+        _ => Duration::from_secs_f64(1.0 / frames_per_second(rate) as f64),
+    }
+}
+
+fn frames_per_day(rate: TimecodeRate) -> i64 {
+    frame_number(&Timecode { hours: 24, minutes: 0, seconds: 0, frames: 0, rate })
+}
+
+// Frames since midnight, leaving out the frame numbers drop frame skips.
+pub fn frame_number(tc: &Timecode) -> i64 {
+    let total_minutes = tc.hours as i64 * 60 + tc.minutes as i64;
+    let nominal = (total_minutes * 60 + tc.seconds as i64) * frames_per_second(tc.rate) as i64 + tc.frames as i64;
+    match tc.rate {
+        TimecodeRate::DropFrame => nominal - 2 * (total_minutes - total_minutes / 10),
+        _ => nominal,
+    }
+}
+
+pub fn timecode_data(source: &str, timecode: Timecode) -> TimecodeData {
+    TimecodeData {
+        timestamp: Local::now().format("%H:%M:%S%.3f").to_string(),
+        source: source.to_string(),
+        timecode,
+        drift_ms: 0.0,
+        dropouts: 0,
+        skipped_frames: 0,
+        jumps: 0,
+    }
+}
+
+pub fn format(tc: &Timecode) -> String {
+    let separator = if tc.rate == TimecodeRate::DropFrame { ';' } else { ':' };
+    format!("{:02}:{:02}:{:02}{}{:02}", tc.hours, tc.minutes, tc.seconds, separator, tc.frames)
+}
+
+struct SourceState {
+    last: Timecode,
+    last_at: Instant,
+    // Frames counted on from the first one seen, so drift carries across midnight.
+    frame: i64,
+    drift_ms: f64,
+    // Where drift is measured from: local time and frame count.
+    anchor_at: Instant,
+    anchor_frame: i64,
+    // Set once `expire` has counted the current dropout.
+    dropped: bool,
+    dropouts: u64,
+    skipped_frames: u64,
+    jumps: u64,
+}
+
+// Compares each timecode source against the local clock.
+#[derive(Default)]
+pub struct TimecodeTracker {
+    sources: Mutex<HashMap<String, SourceState>>,
+}
+
+impl TimecodeTracker {
+    // Fills in drift and the error counters. Returns false for a repeat of the
+    // previous frame, which MTC sends once per quarter frame.
+    pub fn update(&self, data: &mut TimecodeData) -> bool {
+        let now = Instant::now();
+        let tc = data.timecode;
+        let frame = frame_number(&tc);
+        let mut sources = self.sources.lock().unwrap();
+
+        let Some(state) = sources.get_mut(&data.source) else {
+            sources.insert(data.source.clone(), SourceState {
+                last: tc,
+                last_at: now,
+                frame,
+                drift_ms: 0.0,
+                anchor_at: now,
+                anchor_frame: frame,
+                dropped: false,
+                dropouts: 0,
+                skipped_frames: 0,
+                jumps: 0,
+            });
+            return true;
+        };
+        if tc == state.last {
+            return false;
+        }
+
+        let period = frame_duration(tc.rate).as_secs_f64();
+        // 23:59:59:xx to 00:00:00:00 is one frame on, not a day back.
+        let advanced = (frame - frame_number(&state.last)).rem_euclid(frames_per_day(tc.rate));
+        let mut resync = tc.rate != state.last.rate;
+        if state.dropped || is_dropout(state, now) {
+            if !state.dropped {
+                state.dropouts += 1;
+            }
+            resync = true;
+        } else if advanced == 0 || advanced > MAX_SKIP {
+            state.jumps += 1;
+            resync = true;
+        } else {
+            state.skipped_frames += (advanced - 1) as u64;
+        }
+        if resync {
+            state.frame = frame;
+            state.anchor_at = now;
+            state.anchor_frame = frame;
+        } else {
+            state.frame += advanced;
+        }
+        state.last = tc;
+        state.last_at = now;
+        state.dropped = false;
+
+        let timecode_elapsed = (state.frame - state.anchor_frame) as f64 * period;
+        let local_elapsed = now.duration_since(state.anchor_at).as_secs_f64();
+        state.drift_ms = (timecode_elapsed - local_elapsed) * 1000.0;
+        fill_counters(state, data);
+        true
+    }
+
+    // Counts a dropout as soon as a source goes quiet rather than when it comes back,
+    // returning the last frame of each source that just dropped out.
+    pub fn expire(&self) -> Vec<TimecodeData> {
+        let now = Instant::now();
+        let mut dropped = Vec::new();
+        for (source, state) in self.sources.lock().unwrap().iter_mut() {
+            if state.dropped || !is_dropout(state, now) {
+                continue;
+            }
+            state.dropped = true;
+            state.dropouts += 1;
+            let mut data = timecode_data(source, state.last);
+            fill_counters(state, &mut data);
+            dropped.push(data);
+        }
+        dropped
+    }
+
+    pub fn reset(&self) {
+        self.sources.lock().unwrap().clear();
+    }
+}
+
+fn is_dropout(state: &SourceState, now: Instant) -> bool {
+    now.duration_since(state.last_at).as_secs_f64() / frame_duration(state.last.rate).as_secs_f64() > DROPOUT_FRAMES
+}
+
+fn fill_counters(state: &SourceState, data: &mut TimecodeData) {
+    data.drift_ms = state.drift_ms;
+    data.dropouts = state.dropouts;
+    data.skipped_frames = state.skipped_frames;
+    data.jumps = state.jumps;
+}
@@ -83,11 +83,11 @@ function ArtnetMonitor({ onClose, config }) {
     }
   };
 
-  // Listen for Art-Net data from backend
+  // Listen for Art-Net data from backend, merged across all sources so they don't flicker
   useEffect(() => {
-    const unlisten = listen('artnet-universe-data', (event) => {
-      if (event.payload.universe === selectedUniverse) {
-        setChannelData(event.payload.channels);
+    const unlisten = listen('dmx-merge', (event) => {
+      if (event.payload.protocol === 'artnet' && event.payload.universe === selectedUniverse) {
+        setChannelData(event.payload.levels);
       }
     });
 
@@ -28,6 +28,9 @@ function DMXMonitor({ onClose, config }) {
   const availableUniverses = parseUniverses(config.universes);
   const [selectedUniverse, setSelectedUniverse] = useState(availableUniverses[0]);
   const [channelData, setChannelData] = useState(new Array(512).fill(0));
+  // Effective priority per channel and the source holding it, null where nothing drives the channel
+  const [priorityData, setPriorityData] = useState(new Array(512).fill(null));
+  const [showPriority, setShowPriority] = useState(false);
   const [highlightChannel, setHighlightChannel] = useState(null);
   const [viewMode, setViewMode] = useState('grid');
   const [isMinimized, setIsMinimized] = useState(false);
@@ -80,6 +83,7 @@ function DMXMonitor({ onClose, config }) {
 
     setSelectedUniverse(universe);
     setChannelData(new Array(512).fill(0));
+    setPriorityData(new Array(512).fill(null));
 
     try {
       await invoke('sacn_subscribe_universe', { universe });
@@ -88,11 +92,20 @@ function DMXMonitor({ onClose, config }) {
     }
   };
 
-  // Listen for DMX data from backend
+  // Listen for DMX data from backend, merged across all sources so they don't flicker
   useEffect(() => {
-    const unlisten = listen('dmx-universe-data', (event) => {
-      if (event.payload.universe === selectedUniverse) {
-        setChannelData(event.payload.channels);
+    const unlisten = listen('dmx-merge', (event) => {
+      if (event.payload.protocol === 'sacn' && event.payload.universe === selectedUniverse) {
+        const { levels, owners, sources } = event.payload;
+        setChannelData(levels);
+        setPriorityData(owners.map((owner, i) => {
+          if (owner === null) return null;
+          const source = sources[owner];
+          return {
+            priority: source.address_priorities ? source.address_priorities[i] : source.priority,
+            source: source.name,
+          };
+        }));
       }
     });
 
@@ -108,6 +121,12 @@ function DMXMonitor({ onClose, config }) {
     return `rgba(167, 139, 250, ${intensity * 0.6})`;
   };
 
+  // Priorities run 0-200
+  const getPriorityColor = (priority) => {
+    if (!priority) return 'transparent';
+    return `rgba(96, 165, 250, ${(priority / 200) * 0.6})`;
+  };
+
   // Get text color based on background intensity
   const getTextColor = (value) => {
     if (value > 180) return 'var(--bg-primary)';
@@ -198,6 +217,17 @@ function DMXMonitor({ onClose, config }) {
               <rect x="14" y="14" width="7" height="7" />
             </svg>
           </button>
+          <button
+            className={`control-btn ${showPriority ? 'active' : ''}`}
+            onClick={() => setShowPriority(!showPriority)}
+            title={showPriority ? 'Show levels' : 'Show priority map'}
+          >
+            <svg viewBox="0 0 24 24" fill="none" stroke="currentColor" strokeWidth="2">
+              <line x1="6" y1="20" x2="6" y2="14" />
+              <line x1="12" y1="20" x2="12" y2="9" />
+              <line x1="18" y1="20" x2="18" y2="4" />
+            </svg>
+          </button>
           <button 
             className="control-btn" 
             onClick={() => setIsMinimized(!isMinimized)}
@@ -256,6 +286,24 @@ function DMXMonitor({ onClose, config }) {
                 <div className="row-label">{row.startChannel}</div>
                 {row.values.map((value, colIndex) => {
                   const channelNum = row.startChannel + colIndex;
+                  const priority = priorityData[channelNum - 1];
+                  if (showPriority) {
+                    return (
+                      <div
+                        key={colIndex}
+                        className={`channel-cell ${priority ? 'active' : ''} ${highlightChannel === channelNum ? 'highlight' : ''}`}
+                        style={{
+                          backgroundColor: getPriorityColor(priority?.priority),
+                          color: getTextColor(priority ? Math.round((priority.priority / 200) * 255) : 0)
+                        }}
+                        onMouseEnter={() => setHighlightChannel(channelNum)}
+                        onMouseLeave={() => setHighlightChannel(null)}
+                        title={priority ? `Ch ${channelNum}: priority ${priority.priority} from ${priority.source}` : `Ch ${channelNum}: no source`}
+                      >
+                        <span className="channel-value">{priority ? priority.priority : '–'}</span>
+                      </div>
+                    );
+                  }
                   return (
                     <div
                       key={colIndex}
@@ -276,7 +324,17 @@ function DMXMonitor({ onClose, config }) {
             ))}
           </div>
 
-          {highlightChannel && (
+          {highlightChannel && showPriority && (
+            <div className="channel-tooltip">
+              <span className="tooltip-channel">Channel {highlightChannel}</span>
+              <span className="tooltip-value">
+                {priorityData[highlightChannel - 1] ? `Priority ${priorityData[highlightChannel - 1].priority}` : 'No source'}
+              </span>
+              <span className="tooltip-percent">{priorityData[highlightChannel - 1]?.source}</span>
+            </div>
+          )}
+
+          {highlightChannel && !showPriority && (
             <div className="channel-tooltip">
               <span className="tooltip-channel">Channel {highlightChannel}</span>
               <span className="tooltip-value">{channelData[highlightChannel - 1]}</span>
@@ -71,13 +71,25 @@ function OSCMonitor({
   }, [isPaused, setMessages]);
 
   // Format OSC arguments for display
+  const formatArg = (arg) => {
+    const hex = (n) => n.toString(16).padStart(2, '0');
+    switch (arg.type) {
+      case 'String': return `"${arg.value}"`;
+      case 'Char': return `'${arg.value}'`;
+      case 'Nil': return 'nil';
+      case 'Inf': return 'inf';
+      case 'Blob': return `<blob ${arg.value.length} bytes>`;
+      case 'Color': return `#${hex(arg.value.red)}${hex(arg.value.green)}${hex(arg.value.blue)}${hex(arg.value.alpha)}`;
+      case 'Midi': return `<midi ${arg.value.port} ${hex(arg.value.status)} ${hex(arg.value.data1)} ${hex(arg.value.data2)}>`;
+      case 'TimeTag': return `<time ${arg.value.seconds}.${arg.value.fractional}>`;
+      case 'Array': return '[' + arg.value.map(formatArg).join(', ') + ']';
+      default: return arg.value;
+    }
+  };
+
   const formatArgs = (args) => {
     if (!args || args.length === 0) return '[]';
-    return '[' + args.map(arg => {
-      if (arg.type === 'String') return `"${arg.value}"`;
-      if (arg.type === 'Nil') return 'nil';
-      return arg.value;
-    }).join(', ') + ']';
+    return '[' + args.map(formatArg).join(', ') + ']';
   };
 
   // Auto-scroll to bottom when new messages arrive